@@ -0,0 +1,49 @@
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+//! Library for reading DN42 registry objects and working with the links between them:
+//! - Parsing object files into [object_reader::RegistryObject]s
+//! - Parsing the schema objects describing the categories and their lookup keys
+//! - Building the [registry_graph::RegistryGraph] of all objects linked by their lookup keys
+//! - Traversing the graph to find related objects and paths between objects
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use registry_core::object_reader::SimpleObjectLine;
+//! use registry_core::registry_graph::{create_registry_graph, LinkInfoSchemaKey, RegistryGraph};
+//! use registry_core::schema::parse_registry_schema;
+//! use registry_core::traversal::{related_objects, RelatedFilter};
+//!
+//! let registry_root = Path::new("/srv/registry");
+//! let (schema, _) = parse_registry_schema(registry_root, true)?;
+//! let (graph, warnings): (RegistryGraph<SimpleObjectLine, LinkInfoSchemaKey>, _) = create_registry_graph(registry_root, &schema, false, false)?;
+//! for warning in warnings {
+//!     eprintln!("{}", warning);
+//! }
+//! let mntner = graph.find("mntner", "DN42-MNT").ok_or("not found")?;
+//! for id in related_objects(&graph, mntner, &RelatedFilter::default()) {
+//!     println!("{}", graph.path(id));
+//! }
+//! # Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+//! ```
+
+/// Registry object files and their key-value content
+pub mod object_reader;
+/// Schema objects describing the categories of the registry
+pub mod schema;
+/// Graph of the registry objects linked by their lookup keys
+pub mod registry_graph;
+/// Searches on the registry graph
+pub mod traversal;
+#[cfg(test)]
+mod test_registry;
+
+use std::error::Error;
+
+/// Library version
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Library name
+pub const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Result with a boxed error, as returned by the functions reading the registry
+pub type BoxResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
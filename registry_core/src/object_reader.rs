@@ -1,34 +1,49 @@
-use crate::modules::util::BoxResult;
+use crate::BoxResult;
 use serde::{Serialize, Serializer};
 use std::cell::RefCell;
 use std::fmt::Debug;
-use std::fs::read_dir;
+use std::fs::{read_dir, File};
+use std::io;
+use std::io::BufRead;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use serde::ser::SerializeMap;
-use crate::modules::util;
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 
-pub(in crate::modules) trait ObjectLine: Debug + Serialize + Clone {
+/// A value of an object attribute, as stored in a [KeyValue]
+pub trait ObjectLine: Debug + Serialize + Clone + Send + Sync {
+    /// Append text to the last value of the attribute (for values continued on the following lines)
     fn append_to_last(key: &mut Vec<Self>, value: &str);
+    /// Add a value of the attribute, found on the given (0-based) line of the object file
     fn push_line(key: &mut Vec<Self>, value: String, line: usize);
 
+    /// The text of the value
     fn get_line_value(&self) -> String;
 }
 
-pub(in crate::modules) type OrderedObjectLine = (usize, String);
-pub(in crate::modules) type SimpleObjectLine = String;
+/// Value along with the (0-based) line of the object file it starts on
+pub type OrderedObjectLine = (usize, String);
+/// Value only
+pub type SimpleObjectLine = String;
 
+/// A registry object file
 #[derive(Debug, Serialize, Clone)]
-pub(in crate::modules) struct RegistryObject<T>
+pub struct RegistryObject<T>
 where
     T: ObjectLine,
 {
+    /// Attributes in the order of the file
     pub key_value: KeyValue<T>,
+    /// Name of the file, i.e. `172.20.0.0_24` or `EXAMPLE-MNT`
     pub filename: String,
 }
+
+/// Attributes of an object with all their values, in the order of their first occurrence.
+/// Serialized as a map of the attribute names to their values.
 #[derive(Debug, Clone)]
 pub struct KeyValue<T>(pub Vec<(String, Vec<T>)>);
 impl<T> KeyValue<T> {
+    /// The values of an attribute
     pub fn get(&self, key: &str) -> Option<&Vec<T>> {
         self.0.iter().find(|(x, _)| x == key).map(|(_, v)| v)
     }
@@ -36,10 +51,12 @@ impl<T> KeyValue<T> {
         self.0.iter_mut().find(|(x, _)| x == key).map(|(_, v)| v)
     }
 
+    /// Whether the object has no attributes
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
 
+    /// The values of all attributes
     pub fn values(&self) -> impl Iterator<Item = &Vec<T>> {
         self.0.iter().map(|(_, v)| v)
     }
@@ -63,7 +80,8 @@ impl<T: Serialize> Serialize for KeyValue<T> {
     }
 }
 
-pub(in crate::modules) struct RegistryObjectIterator<T: ObjectLine> {
+/// Iterator reading the objects of a directory one at a time, see [registry_objects_to_iter]
+pub struct RegistryObjectIterator<T: ObjectLine> {
     _marker: PhantomData<T>,
     paths: Vec<(String, PathBuf)>,
     filename_filter: Vec<String>,
@@ -73,18 +91,22 @@ pub(in crate::modules) struct RegistryObjectIterator<T: ObjectLine> {
 }
 
 impl<T: ObjectLine> RegistryObjectIterator<T> {
+    /// Only return the file names, without reading the files
     pub fn set_enumerate_only(&mut self, state: bool) -> &Self {
         self.enumerate_only = state;
         self
     }
+    /// Skip files whose name contains the text
     pub fn add_filename_filter(&mut self, filter: &str) -> &Self {
         self.filename_filter.push(filter.to_owned());
         self
     }
+    /// Only read the listed attributes
     pub fn add_exclusive_fields(&mut self, list: Vec<String>) -> &Self {
         self.exclusive_fields.replace(Some(list));
         self
     }
+    /// Do not read the listed attributes
     pub fn add_filtered_fields(&mut self, list: Vec<String>) -> &Self {
         self.filtered_fields.replace(Some(list));
         self
@@ -125,7 +147,8 @@ impl<T: ObjectLine> Iterator for RegistryObjectIterator<T> {
     }
 }
 
-pub(in crate::modules) fn registry_objects_to_iter<T: ObjectLine>(registry_root: &Path, sub_path: &Path) -> BoxResult<RegistryObjectIterator<T>> {
+/// Iterate over the objects in a directory below the registry root (i.e. `data/mntner`)
+pub fn registry_objects_to_iter<T: ObjectLine>(registry_root: &Path, sub_path: &Path) -> BoxResult<RegistryObjectIterator<T>> {
     let paths = get_object_paths(registry_root, sub_path)?;
     Ok(RegistryObjectIterator {
         _marker: Default::default(),
@@ -153,28 +176,28 @@ fn get_object_paths(registry_root: &Path, sub_path: &Path) -> BoxResult<Vec<(Str
     Ok(paths)
 }
 
-pub(in crate::modules) fn read_registry_objects<T: ObjectLine>(registry_root: &Path, sub_path: &Path, enumerate_only: bool) -> BoxResult<Vec<RegistryObject<T>>> {
+/// Read all objects in a directory below the registry root (i.e. `data/mntner`), or only their file names if
+/// `enumerate_only` is set
+pub fn read_registry_objects<T: ObjectLine>(registry_root: &Path, sub_path: &Path, enumerate_only: bool) -> BoxResult<Vec<RegistryObject<T>>> {
     let paths = get_object_paths(registry_root, sub_path)?;
 
-    let mut objects = Vec::<RegistryObject<T>>::new();
-
-    for path in paths {
+    // The files are read in parallel, keeping the order of the paths
+    paths.into_par_iter().map(|path| {
         let map = if enumerate_only {
             Default::default()
         } else {
             read_registry_object_kv(&path.1)?
         };
 
-        objects.push(RegistryObject {
+        Ok(RegistryObject {
             key_value: map,
             filename: path.0,
-        });
-    }
-
-    Ok(objects)
+        })
+    }).collect()
 }
 
-pub(in crate::modules) fn read_registry_object_kv<T: ObjectLine>(path: &Path) -> BoxResult<KeyValue<T>> {
+/// Read the attributes of an object file
+pub fn read_registry_object_kv<T: ObjectLine>(path: &Path) -> BoxResult<KeyValue<T>> {
     read_registry_object_kv_filtered(path, &None, &None)
 }
 
@@ -206,11 +229,13 @@ impl ObjectLine for OrderedObjectLine {
 }
 
 
-pub(in crate::modules) fn read_registry_object_kv_filtered<T: ObjectLine>(path: &Path, exclusive_fields: &Option<Vec<String>>,
-                                                                          filtered_fields: &Option<Vec<String>>)
-                                                                          -> BoxResult<KeyValue<T>> {
+/// Read the attributes of an object file, only those listed in `exclusive_fields` (if set) and none of those listed in
+/// `filtered_fields` (if set)
+pub fn read_registry_object_kv_filtered<T: ObjectLine>(path: &Path, exclusive_fields: &Option<Vec<String>>,
+                                                       filtered_fields: &Option<Vec<String>>)
+                                                       -> BoxResult<KeyValue<T>> {
     let mut map : KeyValue<T> = Default::default();
-    let lines = util::read_lines(path)?;
+    let lines = read_lines(path)?;
     let mut last_obj_key: Option<String> = None;
     for (no, line) in lines.into_iter().enumerate() {
         let line = line?;
@@ -248,3 +273,9 @@ pub(in crate::modules) fn read_registry_object_kv_filtered<T: ObjectLine>(path:
 
     Ok(map)
 }
+
+fn read_lines(path: &Path) -> BoxResult<io::Lines<io::BufReader<File>>> {
+    let file = File::open(path)
+        .map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+    Ok(io::BufReader::new(file).lines())
+}
@@ -0,0 +1,356 @@
+use crate::object_reader::{read_registry_objects, ObjectLine, OrderedObjectLine, RegistryObject, SimpleObjectLine};
+use crate::schema::Schema;
+use crate::BoxResult;
+use serde::ser::SerializeStruct;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::path::Path;
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+
+/// Information stored with each link about the line of the object containing the reference
+pub trait LinkInfoType<T: ObjectLine>: Debug + Serialize + Clone + Send + Sync {
+    /// The information of a reference by the attribute `schema_key` on the line
+    fn get_link_info(schema_key: String, line: &T) -> Self;
+}
+
+/// No link information
+pub type LinkInfoNone = ();
+
+impl LinkInfoType<SimpleObjectLine> for LinkInfoNone {
+    fn get_link_info(_: String, _: &SimpleObjectLine) -> Self {}
+}
+
+/// The attribute containing the reference
+pub type LinkInfoSchemaKey = String;
+impl LinkInfoType<SimpleObjectLine> for LinkInfoSchemaKey {
+    fn get_link_info(schema_key: String, _: &SimpleObjectLine) -> Self {
+        schema_key
+    }
+}
+
+/// The (0-based) line of the object file containing the reference
+pub type LinkInfoLineNumberOnly = usize;
+impl LinkInfoType<OrderedObjectLine> for LinkInfoLineNumberOnly {
+    fn get_link_info(_: String, line: &OrderedObjectLine) -> Self {
+        line.0
+    }
+}
+
+
+/// Index of an object in the node list of its [RegistryGraph]
+pub type NodeId = usize;
+
+/// A graph node: an object along with the objects it references and is referenced by
+#[derive(Debug)]
+pub struct LinkedRegistryObject<T: ObjectLine, L: LinkInfoType<T>> {
+    /// Category of the object
+    pub schema_ref: String,
+    /// Directory of the object below `data/`
+    pub data_dir: String,
+    /// The object itself
+    pub object: RegistryObject<T>,
+    forward_links: Vec<(L, NodeId)>,
+    back_links: Vec<(L, NodeId)>,
+}
+
+/// Categories of objects merely listing other objects, whose links are not followed when searching for related objects
+pub const WEAKLY_REFERENCING: [&str; 3] = ["as-set", "route-set", "registry"];
+
+impl<T: ObjectLine, L: LinkInfoType<T>> LinkedRegistryObject<T, L> {
+    /// Objects referencing this object, with the link information of the referencing line
+    pub fn get_back_links(&self) -> &[(L, NodeId)] {
+        &self.back_links
+    }
+    /// Objects referenced by this object, with the link information of the referencing line
+    pub fn get_forward_links(&self) -> &[(L, NodeId)] {
+        &self.forward_links
+    }
+}
+
+/// Registry objects linked by their lookup keys, built by [create_registry_graph]. The objects are stored in a single
+/// list and refer to each other by [NodeId], so the graph is immutable once built, `Send + Sync` and can be shared
+/// between threads behind an `Arc`.
+#[derive(Debug)]
+pub struct RegistryGraph<T: ObjectLine, L: LinkInfoType<T>> {
+    nodes: Vec<LinkedRegistryObject<T, L>>,
+    /// Nodes of each category, in the order they were read
+    categories: HashMap<String, Vec<NodeId>>,
+}
+
+impl<T: ObjectLine, L: LinkInfoType<T>> Default for RegistryGraph<T, L> {
+    fn default() -> Self {
+        RegistryGraph { nodes: Vec::new(), categories: HashMap::new() }
+    }
+}
+
+impl<T: ObjectLine, L: LinkInfoType<T>> RegistryGraph<T, L> {
+    /// The node with the ID, which must be from this graph
+    pub fn node(&self, id: NodeId) -> &LinkedRegistryObject<T, L> {
+        &self.nodes[id]
+    }
+
+    /// All nodes along with their IDs
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &LinkedRegistryObject<T, L>)> {
+        self.nodes.iter().enumerate()
+    }
+
+    /// Number of nodes, all IDs are below it
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Node IDs by category
+    pub fn categories(&self) -> &HashMap<String, Vec<NodeId>> {
+        &self.categories
+    }
+
+    /// Node IDs of a category, `None` if there is no such category
+    pub fn category(&self, category: &str) -> Option<&[NodeId]> {
+        self.categories.get(category).map(|x| x.as_slice())
+    }
+
+    /// Node of the object with exactly this name
+    pub fn find(&self, category: &str, name: &str) -> Option<NodeId> {
+        self.category(category)?.iter().copied()
+            .find(|id| self.nodes[*id].object.filename == name)
+    }
+
+    /// `category/name` of a node, as used to refer to objects in the output
+    pub fn path(&self, id: NodeId) -> String {
+        let node = &self.nodes[id];
+        format!("{}/{}", node.schema_ref, node.object.filename)
+    }
+
+    /// Serializable form of a node with its links as object paths
+    pub fn serializable(&self, id: NodeId) -> SerializableNode<'_, T, L> {
+        SerializableNode { graph: self, id }
+    }
+}
+
+/// Graph with the line numbers of values and references, which the registry_wizard servers are built on
+pub type ServerGraph = RegistryGraph<OrderedObjectLine, LinkInfoLineNumberOnly>;
+
+/// A graph node serialized like `{"schema_ref", "data_dir", "object", "forward_links", "back_links"}`,
+/// with the links as `category/name` paths
+pub struct SerializableNode<'a, T: ObjectLine, L: LinkInfoType<T>> {
+    graph: &'a RegistryGraph<T, L>,
+    id: NodeId,
+}
+
+impl<T: ObjectLine, L: LinkInfoType<T>> Serialize for SerializableNode<'_, T, L> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let node = self.graph.node(self.id);
+        let link_paths = |links: &[(L, NodeId)]| links.iter().map(|x| self.graph.path(x.1)).collect::<Vec<_>>();
+        let mut state = s.serialize_struct("LinkedRegistryObject", 5)?;
+        state.serialize_field("schema_ref", &node.schema_ref)?;
+        state.serialize_field("data_dir", &node.data_dir)?;
+        state.serialize_field("object", &node.object)?;
+        state.serialize_field("forward_links", &link_paths(&node.forward_links))?;
+        state.serialize_field("back_links", &link_paths(&node.back_links))?;
+        state.end()
+    }
+}
+
+impl<T: ObjectLine, L: LinkInfoType<T>> Serialize for RegistryGraph<T, L> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.categories.iter().map(|(category, ids)| {
+            (category, ids.iter().map(|id| self.serializable(*id)).collect::<Vec<_>>())
+        }))
+    }
+}
+
+/// Read the objects of all categories described by the schema and link them by their lookup keys. References are
+/// compared to the uppercase object names. With `duplicate_forward_links`, an object gets a forward link for every line referencing
+/// the same object instead of only the first one, and with `self_in_forward_links` references to the object itself are
+/// kept as forward links. Back links are always unique and never link an object to itself.
+///
+/// Missing directories and references to objects that do not exist are returned as warnings along with the graph.
+pub fn create_registry_graph<T, L>(registry_root: &Path, registry_schema: &[Schema],
+                                   duplicate_forward_links: bool, self_in_forward_links: bool) -> BoxResult<(RegistryGraph<T, L>, Vec<String>)>
+where
+    T: ObjectLine,
+    L: LinkInfoType<T>,
+{
+    let mut graph: RegistryGraph<T, L> = RegistryGraph::default();
+    let mut warnings = Vec::new();
+
+    // The directories, and the files within each of them, are read in parallel
+    let directories: Vec<_> = registry_schema.par_iter()
+        .map(|schema| (schema, read_registry_objects(registry_root, Path::new(&("data/".to_owned() + &schema.dir_name)), false)))
+        .collect();
+    for (schema, objects) in directories {
+        if objects.is_err() {
+            warnings.push(format!("Error accessing directory referred to by schema: {}", schema.dir_name));
+            continue;
+        }
+        for object in objects? {
+            graph.categories.entry(schema.schema_ref.clone()).or_default().push(graph.nodes.len());
+            graph.nodes.push(LinkedRegistryObject {
+                schema_ref: schema.schema_ref.clone(),
+                object,
+                forward_links: vec![],
+                back_links: vec![],
+                data_dir: schema.dir_name.clone(),
+            })
+        }
+    }
+
+    // Index of each category by the uppercase object name, which references are compared to
+    let mut name_index = HashMap::with_capacity(graph.categories.len());
+    for (category, ids) in &graph.categories {
+        let index = name_index.entry(category.as_str()).or_insert_with(|| HashMap::with_capacity(ids.len()));
+        for id in ids {
+            index.entry(graph.nodes[*id].object.filename.to_uppercase()).or_insert(*id);
+        }
+    }
+    // Established links as (source, target) pairs, so that duplicates are found without scanning the link lists
+    let mut forward_link_pairs = HashSet::new();
+    let mut back_link_pairs = HashSet::new();
+    // The links are collected separately, as the nodes can not be modified while they are being looked up
+    let mut forward_links: Vec<Vec<(L, NodeId)>> = (0..graph.nodes.len()).map(|_| Vec::new()).collect();
+    let mut back_links: Vec<Vec<(L, NodeId)>> = (0..graph.nodes.len()).map(|_| Vec::new()).collect();
+
+    // Establish links
+    for (id, object) in graph.nodes.iter().enumerate() {
+        // For each object regardless of category
+
+        let applicable_schema = registry_schema.iter().find(|x| x.schema_ref == *object.schema_ref).unwrap();
+        let schema_links = &applicable_schema.keys;
+        for schema_link in schema_links {
+            let schema_link_targets = &schema_link.lookup_targets;
+            let schema_key = &schema_link.key;
+            // For each schema_key described in the applicable schema linking via 'lookup=' to a schema_link_target
+
+            // Try to find the schema_key in the object
+            let object_key_values = object.object.key_value.get(schema_key);
+            if object_key_values.is_none() {
+                // Not found
+                continue;
+            }
+
+            // For each found schema key in the object (for instance mnt-by)
+            for object_key_value in object_key_values.unwrap() {
+                // Get all 'lookup=' targets
+                let mut found_valid_category = false;
+                for possible_category in schema_link_targets {
+                    let t_category = name_index.get(possible_category.as_str());
+                    if t_category.is_none() {
+                        warnings.push(format!("Error: unknown category \"{}\"", possible_category));
+                        continue;
+                    }
+                    let target_id = t_category.unwrap().get(&object_key_value.get_line_value());
+                    if target_id.is_none() {
+                        continue;
+                    }
+                    let target_id = *target_id.unwrap();
+                    found_valid_category = true;
+
+
+                    // -------- Add links to current object --------
+                    if self_in_forward_links || target_id != id {
+                        let is_new = forward_link_pairs.insert((id, target_id));
+                        if is_new || duplicate_forward_links {
+                            forward_links[id].push((L::get_link_info(schema_key.clone(), object_key_value), target_id));
+                        }
+                    }
+                    // ----------------------------
+
+                    // -------- Add backlinks to target object --------
+                    if id != target_id && back_link_pairs.insert((target_id, id)) {
+                        back_links[target_id].push((L::get_link_info(schema_key.clone(), object_key_value), id));
+                    }
+                    // ----------------------------
+                }
+                if !found_valid_category  && !schema_link_targets.is_empty() {
+                    warnings.push(format!("Warning: Invalid target object {} for {}, shema key: {}", object_key_value.get_line_value(), object.object.filename, schema_key));
+                }
+            }
+        }
+    }
+    for ((node, forward), back) in graph.nodes.iter_mut().zip(forward_links).zip(back_links) {
+        node.forward_links = forward;
+        node.back_links = back;
+    }
+
+    Ok((graph, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::parse_registry_schema;
+    use crate::test_registry::TestRegistry;
+
+    fn sorted_paths<T: ObjectLine, L: LinkInfoType<T>>(graph: &RegistryGraph<T, L>, links: &[(L, NodeId)]) -> Vec<String> {
+        let mut paths: Vec<String> = links.iter().map(|x| graph.path(x.1)).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn links_objects_by_lookup_keys() {
+        let registry = TestRegistry::new("links_objects_by_lookup_keys");
+        let (schema, _) = parse_registry_schema(&registry.root, true).unwrap();
+        let (graph, _): (RegistryGraph<SimpleObjectLine, LinkInfoSchemaKey>, _) =
+            create_registry_graph(&registry.root, &schema, false, false).unwrap();
+        assert_eq!(graph.node_count(), 11);
+        assert_eq!(graph.category("aut-num").map(|x| x.len()), Some(3));
+        assert!(graph.category("role").is_none());
+
+        let route = graph.find("route", "172.20.0.0_24").unwrap();
+        assert_eq!(graph.path(route), "route/172.20.0.0_24");
+        let forward: Vec<(&str, String)> = graph.node(route).get_forward_links().iter()
+            .map(|(key, id)| (key.as_str(), graph.path(*id)))
+            .collect();
+        assert_eq!(forward, [("origin", String::from("aut-num/AS4242420000")), ("mnt-by", String::from("mntner/FOO-MNT"))]);
+
+        // The lowercase reference of the person does not match, and the reference of the mntner to itself is no back link
+        let mntner = graph.find("mntner", "FOO-MNT").unwrap();
+        assert_eq!(sorted_paths(&graph, graph.node(mntner).get_back_links()),
+                   ["as-set/AS4242420000:AS-ALL", "aut-num/AS4242420000", "route/172.20.0.0_24"]);
+        assert_eq!(sorted_paths(&graph, graph.node(mntner).get_forward_links()), ["person/FOO-DN42"]);
+
+        // The registry key is excluded
+        let registry_object = graph.find("registry", "DN42").unwrap();
+        assert!(graph.node(registry_object).get_back_links().is_empty());
+        assert!(graph.find("mntner", "foo-mnt").is_none());
+    }
+
+    #[test]
+    fn self_and_duplicate_links() {
+        let registry = TestRegistry::new("self_and_duplicate_links");
+        let (schema, _) = parse_registry_schema(&registry.root, false).unwrap();
+
+        let (graph, _): (ServerGraph, _) = create_registry_graph(&registry.root, &schema, true, true).unwrap();
+        let aut_num = graph.find("aut-num", "AS4242420000").unwrap();
+        let forward: Vec<(usize, String)> = graph.node(aut_num).get_forward_links().iter()
+            .map(|(line, id)| (*line, graph.path(*id)))
+            .collect();
+        assert_eq!(forward, [(1, String::from("person/FOO-DN42")), (2, String::from("person/FOO-DN42")),
+                             (4, String::from("mntner/FOO-MNT")), (5, String::from("registry/DN42"))]);
+        let person = graph.find("person", "FOO-DN42").unwrap();
+        assert_eq!(sorted_paths(&graph, graph.node(person).get_back_links()), ["aut-num/AS4242420000", "mntner/FOO-MNT"]);
+        assert_eq!(sorted_paths(&graph, graph.node(person).get_forward_links()), ["registry/DN42"]);
+        let mntner = graph.find("mntner", "FOO-MNT").unwrap();
+        assert!(graph.node(mntner).get_forward_links().iter().any(|x| x.1 == mntner));
+        assert!(!graph.node(mntner).get_back_links().iter().any(|x| x.1 == mntner));
+
+        let (graph, _): (ServerGraph, _) = create_registry_graph(&registry.root, &schema, false, false).unwrap();
+        let forward: Vec<usize> = graph.node(aut_num).get_forward_links().iter().map(|x| x.0).collect();
+        assert_eq!(forward, [1, 4, 5]);
+        assert!(!graph.node(mntner).get_forward_links().iter().any(|x| x.1 == mntner));
+    }
+
+    #[test]
+    fn returns_warnings() {
+        let registry = TestRegistry::new("returns_warnings");
+        let (schema, schema_warnings) = parse_registry_schema(&registry.root, false).unwrap();
+        assert!(schema_warnings.is_empty());
+        let (_, warnings): (RegistryGraph<SimpleObjectLine, LinkInfoNone>, _) =
+            create_registry_graph(&registry.root, &schema, false, false).unwrap();
+        assert!(warnings.contains(&String::from("Error accessing directory referred to by schema: role")));
+        assert!(warnings.contains(&String::from("Warning: Invalid target object MISSING-DN42 for AS4242420000, shema key: tech-c")));
+        assert!(warnings.contains(&String::from("Warning: Invalid target object foo-mnt for FOO-DN42, shema key: mnt-by")));
+    }
+}
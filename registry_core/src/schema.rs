@@ -0,0 +1,117 @@
+use crate::object_reader::{read_registry_objects, RegistryObject, SimpleObjectLine};
+use crate::BoxResult;
+use serde::Serialize;
+use std::path::Path;
+
+/// A category of the registry as described by its schema object
+#[derive(Debug, Serialize)]
+pub struct Schema {
+    /// Directory of the objects below `data/`
+    pub dir_name: String,
+    /// Name of the category, i.e. `mntner`
+    pub schema_ref: String,
+    /// The attributes of the objects
+    pub keys: Vec<SchemaField>,
+}
+
+/// An attribute of the objects of a category
+#[derive(Debug, Serialize)]
+pub struct SchemaField {
+    /// Name of the attribute
+    pub key: String,
+    /// Whether objects must have the attribute
+    pub required: bool,
+    /// Categories the values refer to objects of (the `lookup=` categories)
+    pub lookup_targets: Vec<String>,
+}
+
+
+/// Parse the schema objects in `data/schema`. With `exclude_registry_key`, references to the registry object are not
+/// treated as lookup keys. Malformed schema objects are skipped and returned as warnings along with the schema.
+pub fn parse_registry_schema(registry_root: &Path, exclude_registry_key: bool) -> BoxResult<(Vec<Schema>, Vec<String>)> {
+    let mut schemata = Vec::<Schema>::new();
+    let mut warnings = Vec::new();
+
+    let schema_objects: Vec<RegistryObject<SimpleObjectLine>> = read_registry_objects(registry_root, Path::new("data/schema"), false)?;
+    for schema_object in schema_objects {
+        let schema_ref = schema_object.key_value.get("ref")
+            .and_then(|x| x.first());
+        if schema_ref.is_none() {
+            warnings.push(format!("Error: schema object missing 'ref' key: {}", schema_object.filename));
+            continue;
+        }
+        let schema_ref = match schema_ref.unwrap().strip_prefix("dn42.") {
+            None => {
+                schema_ref.unwrap()
+            }
+            Some(x) => {
+                if x.contains('.') {
+                    warnings.push(format!("Warning: schema ref does not contain '.': {}", schema_object.filename));
+                }
+                x
+            }
+        };
+        
+        
+        let dir_name_first = schema_object.key_value.get("dir-name")
+            .and_then(|x| x.first());
+        let dir_name: String = match dir_name_first {
+            Some(x) => {
+               x.to_string()
+            }
+            None => {
+                schema_ref.to_string()
+            }
+        };
+
+
+        let key_option = schema_object.key_value.get("key");
+        if key_option.is_none() {
+            warnings.push(format!("Error: schema object missing 'key' key: {}", schema_object.filename));
+            continue;
+        }
+
+        let mut schema_keys: Vec<SchemaField> = Vec::new();
+        for key in key_option.unwrap() {
+            let key_line = key.split_whitespace().collect::<Vec<&str>>();
+            let required_field = *key_line.get(1).unwrap_or(&"") == "required";
+
+            let lookup_key_target_position = key_line.get(3).unwrap_or(&"");
+            let mut lookup_key_targets: Vec<String> = Vec::new();
+            if lookup_key_target_position.starts_with("lookup=") {
+                lookup_key_targets = lookup_key_target_position.strip_prefix("lookup=")
+                    .unwrap().split(',')
+                    .map(|x| {
+                        if let Some(x) = x.strip_prefix("dn42.") {
+                            x.to_string()
+                        } else {
+                            if x.contains('.') {
+                                warnings.push(format!("Warning: schema lookup key '{}' does not contain '.': {}", x, schema_object.filename));
+                            }
+                            x.to_string()
+                        }
+                    })
+                    .collect::<Vec<String>>();
+                if exclude_registry_key {
+                    lookup_key_targets.retain(|x| x != "registry")
+                }
+            }
+
+            let lookup_key = key_line.first().unwrap();
+            schema_keys.push(SchemaField {
+                key: lookup_key.to_string(),
+                required: required_field,
+                lookup_targets: lookup_key_targets,
+            })
+        }
+
+
+        schemata.push(Schema {
+            dir_name,
+            keys: schema_keys,
+            schema_ref: schema_ref.to_string(),
+        });
+    }
+
+    Ok((schemata, warnings))
+}
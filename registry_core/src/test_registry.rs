@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Schema objects of the test registry as (category, keys). `role` has no directory.
+const SCHEMATA: [(&str, &[&str]); 7] = [
+    ("mntner", &["mntner required single primary", "admin-c optional multiple lookup=dn42.person,dn42.role", "mnt-by required multiple lookup=dn42.mntner", "source required single lookup=dn42.registry"]),
+    ("person", &["nic-hdl required single primary", "mnt-by required multiple lookup=dn42.mntner", "source required single lookup=dn42.registry"]),
+    ("role", &["nic-hdl required single primary", "mnt-by required multiple lookup=dn42.mntner"]),
+    ("aut-num", &["aut-num required single primary", "admin-c optional multiple lookup=dn42.person,dn42.role", "tech-c optional multiple lookup=dn42.person,dn42.role", "mnt-by required multiple lookup=dn42.mntner", "source required single lookup=dn42.registry"]),
+    ("route", &["route required single primary", "origin required multiple lookup=dn42.aut-num", "mnt-by required multiple lookup=dn42.mntner", "source required single lookup=dn42.registry"]),
+    ("as-set", &["as-set required single primary", "members optional multiple lookup=dn42.aut-num,dn42.as-set", "mnt-by required multiple lookup=dn42.mntner", "source required single lookup=dn42.registry"]),
+    ("registry", &["registry required single primary", "mnt-by required multiple lookup=dn42.mntner"]),
+];
+
+/// Category, name and lines of an object
+type TestObject = (&'static str, &'static str, &'static [(&'static str, &'static str)]);
+
+/// Objects of the test registry. FOO-MNT and BAR-MNT are only connected through
+/// the as-set and the registry object. AS4242420000 references a person that does not exist and FOO-DN42 its
+/// mntner in lowercase.
+const OBJECTS: [TestObject; 11] = [
+    ("registry", "DN42", &[("registry", "DN42"), ("mnt-by", "DN42-MNT")]),
+    ("mntner", "DN42-MNT", &[("mntner", "DN42-MNT"), ("mnt-by", "DN42-MNT"), ("source", "DN42")]),
+    ("mntner", "FOO-MNT", &[("mntner", "FOO-MNT"), ("admin-c", "FOO-DN42"), ("mnt-by", "FOO-MNT"), ("source", "DN42")]),
+    ("person", "FOO-DN42", &[("nic-hdl", "FOO-DN42"), ("mnt-by", "foo-mnt"), ("source", "DN42")]),
+    ("aut-num", "AS4242420000", &[("aut-num", "AS4242420000"), ("admin-c", "FOO-DN42"), ("tech-c", "FOO-DN42"),
+        ("tech-c", "MISSING-DN42"), ("mnt-by", "FOO-MNT"), ("source", "DN42")]),
+    ("route", "172.20.0.0_24", &[("route", "172.20.0.0/24"), ("origin", "AS4242420000"), ("mnt-by", "FOO-MNT"), ("source", "DN42")]),
+    ("mntner", "BAR-MNT", &[("mntner", "BAR-MNT"), ("admin-c", "BAR-DN42"), ("mnt-by", "BAR-MNT"), ("source", "DN42")]),
+    ("person", "BAR-DN42", &[("nic-hdl", "BAR-DN42"), ("mnt-by", "BAR-MNT"), ("source", "DN42")]),
+    ("aut-num", "AS4242420001", &[("aut-num", "AS4242420001"), ("admin-c", "BAR-DN42"), ("mnt-by", "BAR-MNT"), ("source", "DN42")]),
+    ("aut-num", "AS0", &[("aut-num", "AS0"), ("mnt-by", "DN42-MNT"), ("source", "DN42")]),
+    ("as-set", "AS4242420000:AS-ALL", &[("as-set", "AS4242420000:AS-ALL"), ("members", "AS4242420000"), ("members", "AS4242420001"),
+        ("mnt-by", "FOO-MNT"), ("source", "DN42")]),
+];
+
+/// Small registry written to a temporary directory, which is removed again when dropped
+pub(crate) struct TestRegistry {
+    pub root: PathBuf,
+}
+
+impl TestRegistry {
+    /// Write the registry to a directory named after the test, as the tests run in parallel
+    pub fn new(test_name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("registry_core_{}_{}", std::process::id(), test_name));
+        let data = root.join("data");
+        let _ = fs::remove_dir_all(&root);
+        for (category, keys) in SCHEMATA {
+            let mut lines = vec![("ref", format!("dn42.{}", category))];
+            lines.extend(keys.iter().map(|x| ("key", x.to_string())));
+            write_object(&data, "schema", &format!("{}-SCHEMA", category.to_uppercase()), &lines);
+        }
+        for (category, name, lines) in OBJECTS {
+            let lines: Vec<_> = lines.iter().map(|(key, value)| (*key, value.to_string())).collect();
+            write_object(&data, category, name, &lines);
+        }
+        TestRegistry { root }
+    }
+}
+
+impl Drop for TestRegistry {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+fn write_object(data: &Path, category: &str, name: &str, lines: &[(&str, String)]) {
+    let content: String = lines.iter()
+        .map(|(key, value)| format!("{:<20}{}\n", format!("{}:", key), value))
+        .collect();
+    fs::create_dir_all(data.join(category)).unwrap();
+    fs::write(data.join(category).join(name), content).unwrap();
+}
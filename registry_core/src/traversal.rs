@@ -0,0 +1,248 @@
+use crate::object_reader::{ObjectLine, RegistryObject};
+use crate::registry_graph::{LinkInfoType, NodeId, RegistryGraph, WEAKLY_REFERENCING};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Objects reached by a graph traversal in the order they were found, along with a set of them for constant time lookups
+pub struct VisitedObjects {
+    /// The visited objects, starting with the first one
+    pub list: Vec<NodeId>,
+    set: HashSet<NodeId>,
+}
+
+impl VisitedObjects {
+    /// Start a traversal at the object
+    pub fn new(start: NodeId) -> Self {
+        VisitedObjects {
+            list: vec![start],
+            set: HashSet::from([start]),
+        }
+    }
+
+    /// Add the object, returning whether it was not visited before
+    pub fn insert(&mut self, id: NodeId) -> bool {
+        if !self.set.insert(id) {
+            return false;
+        }
+        self.list.push(id);
+        true
+    }
+}
+
+/// Mark the objects linked to the object (in both directions) as visited and queue those not visited before
+pub fn link_visit<T: ObjectLine, L: LinkInfoType<T>>(
+    graph: &RegistryGraph<T, L>, id: NodeId, visited: &mut VisitedObjects, to_visit: &mut Vec<NodeId>,
+) {
+    let obj = graph.node(id);
+    for link in obj.get_forward_links().iter().chain(obj.get_back_links()) {
+        // Do not visit a vertex twice
+        if visited.insert(link.1) {
+            to_visit.push(link.1);
+        }
+    }
+}
+
+/// Filters of the related objects search, shared by the CLI and the explorer
+#[derive(Default)]
+pub struct RelatedFilter {
+    /// Only output objects maintained by this mntner
+    pub enforce_mnt_by: Option<String>,
+    /// Do not follow the links of objects maintained by another mntner
+    pub only_related_to_mnt: Option<String>,
+    /// Only output objects not containing this text in any value
+    pub not_contain_value: Option<String>,
+    /// Only output objects containing this text in a value
+    pub contain_value: Option<String>,
+    /// Do not follow the links of objects maintained by DN42-MNT
+    pub no_cross_dn42_mnt: bool,
+}
+
+impl RelatedFilter {
+    /// Whether the links of a visited object are followed
+    pub fn follow_links<T: ObjectLine>(&self, category: &str, object: &RegistryObject<T>) -> bool {
+        if WEAKLY_REFERENCING.contains(&category) {
+            return false;
+        }
+        if self.no_cross_dn42_mnt && let Some(m) = object.key_value.get("mnt-by")
+            && m.iter().any(|x| x.get_line_value() == "DN42-MNT") {
+            return false;
+        }
+        if let Some(ref target) = self.only_related_to_mnt
+            && let Some(m) = object.key_value.get("mnt-by")
+            && m.iter().any(|x| &x.get_line_value() != target) {
+            return false;
+        }
+        true
+    }
+
+    /// Whether a visited object is part of the result
+    pub fn retain<T: ObjectLine>(&self, object: &RegistryObject<T>) -> bool {
+        if let Some(ref target) = self.enforce_mnt_by
+            && let Some(m) = object.key_value.get("mnt-by")
+            && m.iter().any(|x| &x.get_line_value() != target) {
+            return false;
+        }
+        let contains = |value: &str| object.key_value.values().any(|x| {
+            x.iter().any(|s| s.get_line_value().contains(value))
+        });
+        if let Some(ref value) = self.not_contain_value && contains(value) {
+            return false;
+        }
+        if let Some(ref value) = self.contain_value && !contains(value) {
+            return false;
+        }
+        true
+    }
+
+    /// The mntner whose objects are highlighted
+    pub fn highlighted_mnt(&self) -> Option<String> {
+        self.only_related_to_mnt.clone().or(self.enforce_mnt_by.clone())
+    }
+}
+
+/// Objects connected to the start object (including itself) in the order they were found, following the links
+/// of the objects the filter allows and keeping the objects it retains
+pub fn related_objects<T: ObjectLine, L: LinkInfoType<T>>(graph: &RegistryGraph<T, L>, start: NodeId, filter: &RelatedFilter) -> Vec<NodeId> {
+    let mut visited = VisitedObjects::new(start);
+    let mut to_visit = vec![start];
+    while let Some(id) = to_visit.pop() {
+        let obj = graph.node(id);
+        if !filter.follow_links(&obj.schema_ref, &obj.object) {
+            continue;
+        }
+        link_visit(graph, id, &mut visited, &mut to_visit);
+    }
+    let mut visited = visited.list;
+    visited.retain(|x| filter.retain(&graph.node(*x).object));
+    visited
+}
+
+/// Whether searches stop at the object instead of following its links. Weakly referencing objects and AS0 are
+/// referenced by many otherwise unrelated objects.
+pub fn stops_traversal<T: ObjectLine, L: LinkInfoType<T>>(graph: &RegistryGraph<T, L>, id: NodeId) -> bool {
+    let obj = graph.node(id);
+    WEAKLY_REFERENCING.contains(&obj.schema_ref.as_str()) || (obj.schema_ref == "aut-num" && obj.object.filename == "AS0")
+}
+
+/// Shortest path from `src` to `dst` (both included), following links in both directions. Apart from those of the
+/// start object, the links of objects the search [stops at](stops_traversal) are not followed.
+pub fn shortest_path<T: ObjectLine, L: LinkInfoType<T>>(graph: &RegistryGraph<T, L>, src: NodeId, dst: NodeId) -> Option<Vec<NodeId>> {
+    // Perform a breadth-first search, remembering the parent of each visited object
+    let mut parents: HashMap<NodeId, NodeId> = HashMap::new();
+    let mut visited = VisitedObjects::new(src);
+    let mut to_visit = VecDeque::from([src]);
+    let mut found = false;
+    while let Some(id) = to_visit.pop_front() {
+        if id == dst {
+            found = true;
+            break;
+        }
+        if id != src && stops_traversal(graph, id) {
+            continue;
+        }
+        let mut new_links = Vec::new();
+        link_visit(graph, id, &mut visited, &mut new_links);
+        for link in new_links {
+            parents.insert(link, id);
+            to_visit.push_back(link);
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    let mut path = vec![dst];
+    while let Some(parent) = parents.get(path.last().unwrap()) {
+        path.push(*parent);
+    }
+    path.reverse();
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_reader::SimpleObjectLine;
+    use crate::registry_graph::{create_registry_graph, LinkInfoNone};
+    use crate::schema::parse_registry_schema;
+    use crate::test_registry::TestRegistry;
+
+    fn test_graph(registry: &TestRegistry) -> RegistryGraph<SimpleObjectLine, LinkInfoNone> {
+        let (schema, _) = parse_registry_schema(&registry.root, false).unwrap();
+        create_registry_graph(&registry.root, &schema, false, false).unwrap().0
+    }
+
+    fn paths(graph: &RegistryGraph<SimpleObjectLine, LinkInfoNone>, ids: &[NodeId]) -> Vec<String> {
+        ids.iter().map(|x| graph.path(*x)).collect()
+    }
+
+    fn sorted_paths(graph: &RegistryGraph<SimpleObjectLine, LinkInfoNone>, ids: &[NodeId]) -> Vec<String> {
+        let mut paths = paths(graph, ids);
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn related_objects_stop_at_weak_references() {
+        let registry = TestRegistry::new("related_objects_stop_at_weak_references");
+        let graph = test_graph(&registry);
+        let mntner = graph.find("mntner", "FOO-MNT").unwrap();
+
+        let related = related_objects(&graph, mntner, &RelatedFilter::default());
+        assert_eq!(related[0], mntner);
+        // BAR-MNT and DN42-MNT are only linked through the as-set and the registry object
+        assert_eq!(sorted_paths(&graph, &related), ["as-set/AS4242420000:AS-ALL", "aut-num/AS4242420000", "mntner/FOO-MNT",
+                                                    "person/FOO-DN42", "registry/DN42", "route/172.20.0.0_24"]);
+
+        let as_set = graph.find("as-set", "AS4242420000:AS-ALL").unwrap();
+        assert_eq!(related_objects(&graph, as_set, &RelatedFilter::default()), [as_set]);
+    }
+
+    #[test]
+    fn related_objects_filters() {
+        let registry = TestRegistry::new("related_objects_filters");
+        let graph = test_graph(&registry);
+        let mntner = graph.find("mntner", "FOO-MNT").unwrap();
+        let filter = RelatedFilter { not_contain_value: Some(String::from("DN42-MNT")), ..Default::default() };
+        assert!(!paths(&graph, &related_objects(&graph, mntner, &filter)).contains(&String::from("registry/DN42")));
+        let filter = RelatedFilter { contain_value: Some(String::from("172.20.0.0/24")), ..Default::default() };
+        assert_eq!(paths(&graph, &related_objects(&graph, mntner, &filter)), ["route/172.20.0.0_24"]);
+
+        let dn42_mnt = graph.find("mntner", "DN42-MNT").unwrap();
+        assert_eq!(sorted_paths(&graph, &related_objects(&graph, dn42_mnt, &RelatedFilter::default())),
+                   ["aut-num/AS0", "mntner/DN42-MNT", "registry/DN42"]);
+        let filter = RelatedFilter { no_cross_dn42_mnt: true, ..Default::default() };
+        assert_eq!(related_objects(&graph, dn42_mnt, &filter), [dn42_mnt]);
+    }
+
+    #[test]
+    fn shortest_paths() {
+        let registry = TestRegistry::new("shortest_paths");
+        let graph = test_graph(&registry);
+        let find = |category, name| graph.find(category, name).unwrap();
+
+        let path = shortest_path(&graph, find("person", "FOO-DN42"), find("route", "172.20.0.0_24")).unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(path.first(), Some(&find("person", "FOO-DN42")));
+        assert_eq!(path.last(), Some(&find("route", "172.20.0.0_24")));
+        assert_eq!(shortest_path(&graph, find("mntner", "FOO-MNT"), find("mntner", "FOO-MNT")), Some(vec![find("mntner", "FOO-MNT")]));
+
+        // No path through the as-set, the registry object or AS0
+        assert_eq!(shortest_path(&graph, find("mntner", "FOO-MNT"), find("mntner", "BAR-MNT")), None);
+        assert_eq!(shortest_path(&graph, find("mntner", "BAR-MNT"), find("mntner", "DN42-MNT")), None);
+        // The links of the start object are always followed
+        let path = shortest_path(&graph, find("as-set", "AS4242420000:AS-ALL"), find("mntner", "BAR-MNT")).unwrap();
+        assert_eq!(paths(&graph, &path), ["as-set/AS4242420000:AS-ALL", "aut-num/AS4242420001", "mntner/BAR-MNT"]);
+        let path = shortest_path(&graph, find("aut-num", "AS0"), find("mntner", "DN42-MNT")).unwrap();
+        assert_eq!(paths(&graph, &path), ["aut-num/AS0", "mntner/DN42-MNT"]);
+    }
+
+    #[test]
+    fn traversal_stops() {
+        let registry = TestRegistry::new("traversal_stops");
+        let graph = test_graph(&registry);
+        assert!(stops_traversal(&graph, graph.find("as-set", "AS4242420000:AS-ALL").unwrap()));
+        assert!(stops_traversal(&graph, graph.find("registry", "DN42").unwrap()));
+        assert!(stops_traversal(&graph, graph.find("aut-num", "AS0").unwrap()));
+        assert!(!stops_traversal(&graph, graph.find("aut-num", "AS4242420000").unwrap()));
+    }
+}
@@ -0,0 +1,160 @@
+//! Timings of the graph subcommands on a synthetic registry
+//!
+//! Run with `cargo bench -p registry_wizard`. The registry size (number of maintainers, each with a person, aut-num,
+//! inetnum, inet6num, route, route6 and dns object) and the number of runs per command can be set with the
+//! `REGISTRY_BENCH_SIZE` and `REGISTRY_BENCH_RUNS` environment variables.
+
+use std::fs;
+use std::io;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const DEFAULT_SIZE: usize = 5000;
+const DEFAULT_RUNS: usize = 3;
+/// Maintainers are linked to their predecessor through `tech-c`, forming connected groups of this size
+const GROUP_SIZE: usize = 100;
+
+const SCHEMATA: [(&str, &str, &[&str]); 11] = [
+    ("AS-SET-SCHEMA", "as-set", &["as-set required single primary", "members optional multiple lookup=dn42.aut-num,dn42.as-set"]),
+    ("AUT-NUM-SCHEMA", "aut-num", &["aut-num required single primary", "as-name required single", "admin-c optional multiple lookup=dn42.person,dn42.role", "tech-c optional multiple lookup=dn42.person,dn42.role"]),
+    ("DNS-SCHEMA", "dns", &["domain required single primary", "nserver optional multiple", "admin-c optional multiple lookup=dn42.person,dn42.role"]),
+    ("INET6NUM-SCHEMA", "inet6num", &["inet6num required single", "cidr required single primary", "netname required single", "admin-c optional multiple lookup=dn42.person,dn42.role"]),
+    ("INETNUM-SCHEMA", "inetnum", &["inetnum required single", "cidr required single primary", "netname required single", "admin-c optional multiple lookup=dn42.person,dn42.role"]),
+    ("MNTNER-SCHEMA", "mntner", &["mntner required single primary schema", "admin-c optional multiple lookup=dn42.person,dn42.role", "tech-c optional multiple lookup=dn42.person,dn42.role", "auth optional multiple"]),
+    ("PERSON-SCHEMA", "person", &["person required single", "nic-hdl required single primary", "e-mail optional multiple"]),
+    ("REGISTRY-SCHEMA", "registry", &["registry required single primary"]),
+    ("ROLE-SCHEMA", "role", &["role required single", "nic-hdl required single primary"]),
+    ("ROUTE-SCHEMA", "route", &["route required single primary", "origin required multiple lookup=dn42.aut-num", "max-length optional single"]),
+    ("ROUTE6-SCHEMA", "route6", &["route6 required single primary", "origin required multiple lookup=dn42.aut-num", "max-length optional single"]),
+];
+
+fn main() -> io::Result<()> {
+    let size = env_usize("REGISTRY_BENCH_SIZE", DEFAULT_SIZE);
+    let runs = env_usize("REGISTRY_BENCH_RUNS", DEFAULT_RUNS).max(1);
+    let registry_root = std::env::temp_dir().join(format!("registry_wizard_bench_{}", std::process::id()));
+
+    eprintln!("Generating a synthetic registry with {} maintainers in {}", size, registry_root.display());
+    let result = generate_registry(&registry_root, size).and_then(|_| run_benchmarks(&registry_root, size, runs));
+    fs::remove_dir_all(&registry_root)?;
+    result
+}
+
+fn run_benchmarks(registry_root: &Path, size: usize, runs: usize) -> io::Result<()> {
+    let last_of_group = format!("M{}-MNT", GROUP_SIZE.min(size) - 1);
+    let benchmarks: [(&str, Vec<&str>); 5] = [
+        ("graph list", vec!["graph", "list"]),
+        ("graph list (one object)", vec!["graph", "list", "mntner", "M0-MNT"]),
+        ("graph related", vec!["graph", "related", "mntner", "M0-MNT"]),
+        ("graph path", vec!["graph", "path", "mntner", "M0-MNT", "mntner", &last_of_group]),
+        ("remove mnt (with sub-graph check)", vec!["remove", "mnt", "-l", "M1-MNT", "-s"]),
+    ];
+    for (name, args) in &benchmarks {
+        let timings = (0..runs).map(|_| run(registry_root, args)).collect::<io::Result<Vec<_>>>()?;
+        let min = timings.iter().min().copied().unwrap_or_default();
+        let mean = timings.iter().sum::<Duration>() / runs as u32;
+        println!("{:<36} min {:>9.1} ms   mean {:>9.1} ms   ({} runs)", name, as_ms(min), as_ms(mean), runs);
+    }
+    Ok(())
+}
+
+fn run(registry_root: &Path, args: &[&str]) -> io::Result<Duration> {
+    let start = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_registry_wizard"))
+        .arg(registry_root)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    let elapsed = start.elapsed();
+    if !status.success() {
+        return Err(io::Error::other(format!("'{}' failed with {}", args.join(" "), status)));
+    }
+    Ok(elapsed)
+}
+
+fn generate_registry(registry_root: &Path, size: usize) -> io::Result<()> {
+    let data = registry_root.join("data");
+    fs::create_dir_all(data.join("schema"))?;
+    for (schema, category, keys) in SCHEMATA {
+        fs::create_dir_all(data.join(category))?;
+        let mut lines = vec![("schema", schema.to_string()), ("ref", format!("dn42.{}", category))];
+        lines.extend(keys.iter().map(|key| ("key", key.to_string())));
+        lines.push(("key", String::from("mnt-by required multiple lookup=dn42.mntner")));
+        if category != "registry" {
+            lines.push(("key", String::from("source required single lookup=dn42.registry")));
+        }
+        write_object(&data, "schema", schema, &lines)?;
+    }
+
+    write_object(&data, "registry", "DN42", &[("registry", "DN42".into()), ("mnt-by", "DN42-MNT".into())])?;
+    write_object(&data, "mntner", "DN42-MNT", &[("mntner", "DN42-MNT".into()), ("admin-c", "DN42-DN42".into()), ("mnt-by", "DN42-MNT".into())])?;
+    write_object(&data, "person", "DN42-DN42", &[("person", "DN42 admins".into()), ("nic-hdl", "DN42-DN42".into()), ("mnt-by", "DN42-MNT".into())])?;
+
+    let mut as_set_members = Vec::with_capacity(size);
+    for i in 0..size {
+        let mnt = format!("M{}-MNT", i);
+        let person = format!("P{}-DN42", i);
+        let asn = format!("AS{}", 4242420000u64 + i as u64);
+        let base = Ipv4Addr::from(u32::from(Ipv4Addr::new(172, 20, 0, 0)) + (i as u32 % 16384) * 16);
+        let last = Ipv4Addr::from(u32::from(base) + 15);
+        let prefix6 = format!("fd42:{:x}:{:x}::", 0x4000 + i / 0x10000, i % 0x10000);
+
+        let mut mntner = vec![("mntner", mnt.clone()), ("admin-c", person.clone())];
+        if i % GROUP_SIZE != 0 {
+            mntner.push(("tech-c", format!("P{}-DN42", i - 1)));
+        }
+        mntner.push(("auth", String::from("pgp-fingerprint 0000")));
+        write_object(&data, "mntner", &mnt, &with_mnt_by(mntner, &mnt))?;
+        write_object(&data, "person", &person, &with_mnt_by(vec![
+            ("person", format!("Person {}", i)), ("nic-hdl", person.clone()), ("e-mail", format!("p{}@example.com", i)),
+        ], &mnt))?;
+        write_object(&data, "aut-num", &asn, &with_mnt_by(vec![
+            ("aut-num", asn.clone()), ("as-name", format!("AS-{}", i)), ("admin-c", person.clone()), ("tech-c", person.clone()),
+        ], &mnt))?;
+        write_object(&data, "inetnum", &format!("{}_28", base), &with_mnt_by(vec![
+            ("inetnum", format!("{} - {}", base, last)), ("cidr", format!("{}/28", base)),
+            ("netname", format!("NET-{}", i)), ("admin-c", person.clone()),
+        ], &mnt))?;
+        write_object(&data, "route", &format!("{}_28", base), &with_mnt_by(vec![
+            ("route", format!("{}/28", base)), ("origin", asn.clone()),
+        ], &mnt))?;
+        write_object(&data, "inet6num", &format!("{}_48", prefix6), &with_mnt_by(vec![
+            ("inet6num", format!("{} - {}ffff:ffff:ffff:ffff:ffff", prefix6, prefix6)), ("cidr", format!("{}/48", prefix6)),
+            ("netname", format!("NET6-{}", i)), ("admin-c", person.clone()),
+        ], &mnt))?;
+        write_object(&data, "route6", &format!("{}_48", prefix6), &with_mnt_by(vec![
+            ("route6", format!("{}/48", prefix6)), ("origin", asn.clone()),
+        ], &mnt))?;
+        write_object(&data, "dns", &format!("m{}.dn42", i), &with_mnt_by(vec![
+            ("domain", format!("m{}.dn42", i)), ("nserver", format!("ns1.m{}.dn42 {}", i, base)), ("admin-c", person),
+        ], &mnt))?;
+        as_set_members.push(("members", asn));
+    }
+
+    let mut as_set = vec![("as-set", String::from("AS4242420000:AS-ALL"))];
+    as_set.extend(as_set_members);
+    write_object(&data, "as-set", "AS4242420000:AS-ALL", &with_mnt_by(as_set, "DN42-MNT"))
+}
+
+fn with_mnt_by(mut lines: Vec<(&'static str, String)>, mnt: &str) -> Vec<(&'static str, String)> {
+    lines.push(("mnt-by", mnt.to_string()));
+    lines.push(("source", String::from("DN42")));
+    lines
+}
+
+fn write_object(data: &Path, category: &str, name: &str, lines: &[(&str, String)]) -> io::Result<()> {
+    let content: String = lines.iter()
+        .map(|(key, value)| format!("{:<20}{}\n", format!("{}:", key), value))
+        .collect();
+    fs::write(data.join(category).join(name), content)
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    std::env::var(name).ok().and_then(|x| x.parse().ok()).unwrap_or(default)
+}
+
+fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
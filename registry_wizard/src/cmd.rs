@@ -1,8 +1,32 @@
 use crate::modules::util::EitherOr;
+use crate::modules::zone_files::ResolverFormat;
 use crate::{NAME, VERSION};
+use crate::modules::config::{self, Config};
 use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use std::process::exit;
+#[cfg(feature = "daemon")]
+use std::ffi::OsString;
+#[cfg(feature = "explorer")]
+use crate::modules::explorer::{ListenOptions, TlsFiles};
+#[cfg(feature = "daemon")]
+use crate::modules::daemon::{DnsListener, ExplorerListener, Listener, RtrListener, ServeConfig};
+#[cfg(feature = "daemon")]
+use crate::modules::util::BoxResult;
 
-pub fn get_arg_matches() -> ArgMatches {
+pub fn get_arg_matches() -> (ArgMatches, Config) {
+    let command = get_command();
+    let config = match Config::load(config::config_file_from_args(), &command) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    };
+    let matches = config.apply(command).get_matches();
+    (matches, config)
+}
+
+fn get_command() -> Command {
     Command::new(NAME)
         .bin_name(NAME)
         .version(VERSION)
@@ -13,6 +37,11 @@ pub fn get_arg_matches() -> ArgMatches {
                 .help("path to registry root")
                 .required(true)
                 .index(1)
+        ).arg(
+            Arg::new("config")
+                .long("config")
+                .global(true)
+                .help("TOML configuration file with a section per subcommand (also REGISTRY_WIZARD_CONFIG)")
         ).subcommands(
         [
             Command::new("roa")
@@ -30,16 +59,25 @@ pub fn get_arg_matches() -> ArgMatches {
                     .help("Abort program if an error was found in a file")
             ),
             Command::new("dns")
-                .about("DNS zone file and trust anchor generation (for use with PowerDNS)")
+                .about("DNS zone file and trust anchor generation (for use with PowerDNS, Unbound, Knot Resolver, BIND or dnsmasq)")
                 .subcommand_required(true)
+                .arg(
+                    Arg::new("strict")
+                        .short('s')
+                        .long("strict")
+                        .action(ArgAction::SetTrue)
+                        .help("Abort program if an error was found in a dns, inetnum or inet6num object instead of skipping it \
+                               (check: exit with an error after the report, serve: reject the data update)")
+                )
                 .subcommands([
                     Command::new("zones").about("Output zone files")
-                        .arg(
+                        .args([
                             Arg::new("authoritative_servers")
                                 .help("List of default authoritative servers (comma separated)")
                                 .required(true)
-                                .num_args(1..)
-                        ),
+                                .num_args(1..),
+                            resolver_format_arg(),
+                        ]),
                     Command::new("zones-legacy").about("Output zone files (legacy format)")
                         .arg(
                             Arg::new("authoritative_servers")
@@ -47,8 +85,50 @@ pub fn get_arg_matches() -> ArgMatches {
                                 .required(true)
                                 .num_args(1..)
                         ),
-                    Command::new("tas").about("Output trust anchors (lua format)"),
+                    Command::new("tas").about("Output trust anchors (lua format unless another --format is specified)")
+                        .arg(resolver_format_arg()),
                     Command::new("tas-yml").about("Output trust anchors (yml format)"),
+                    Command::new("reverse").about("Output reverse zone files for the dn42 in-addr.arpa and ip6.arpa parents (RFC 1035 format)")
+                        .args([
+                            Arg::new("zones")
+                                .help("Only output the specified zones (i.e. 20.172.in-addr.arpa)")
+                                .num_args(0..),
+                            Arg::new("output_dir")
+                                .long("output-dir")
+                                .short('o')
+                                .help("Write one '<zone>.zone' file per zone to the specified directory instead of stdout"),
+                        ]),
+                    #[cfg(feature = "dns-server")]
+                    Command::new("serve").about("Start an authoritative DNS server (UDP and TCP) for the registry TLDs and reverse zones")
+                        .args([
+                            Arg::new("port")
+                                .long("port")
+                                .short('p')
+                                .value_parser(clap::value_parser!(u16))
+                                .default_value("53")
+                                .help("Port to listen on"),
+                            Arg::new("bind-ip")
+                                .long("bind")
+                                .help("IP address to bind to"),
+                        ]),
+                    Command::new("check").about("Check dns, inetnum and inet6num objects for invalid ds-rdata and nserver entries")
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .short('j')
+                                .action(ArgAction::SetTrue)
+                                .help("Output the report in JSON format")
+                        ),
+                    Command::new("authoritative").about("Output authoritative zone files for the registry TLDs (RFC 1035 format)")
+                        .args([
+                            Arg::new("zones")
+                                .help("Only output the specified zones (i.e. dn42)")
+                                .num_args(0..),
+                            Arg::new("output_dir")
+                                .long("output-dir")
+                                .short('o')
+                                .help("Write one '<zone>.zone' file per zone to the specified directory instead of stdout"),
+                        ]),
                 ]),
             Command::new("object_metadata")
                 .about("Object metadata output (JSON format)")
@@ -76,8 +156,35 @@ pub fn get_arg_matches() -> ArgMatches {
                     Command::new("ip").about("Find the smallest inetnum or inet6num containing an IP address")
                         .args([
                             Arg::new("address").help("The target IP address").required(true)
-                        ])
-                ]),
+                        ]),
+                    Command::new("asn").about("Find the aut-num, the routes originated by and the as-sets containing an ASN")
+                        .args([
+                            Arg::new("asn").help("The target ASN (i.e. AS4242420000 or 4242420000)").required(true)
+                        ]),
+                    Command::new("mnt").about("Find all objects maintained by a mntner")
+                        .args([
+                            Arg::new("mnt").help("The target mntner (i.e. EXAMPLE-MNT)").required(true)
+                        ]),
+                    Command::new("person").about("Find all objects referencing a nic-hdl through admin-c or tech-c")
+                        .args([
+                            Arg::new("nic_hdl").help("The target nic-hdl (i.e. EXAMPLE-DN42)").required(true)
+                        ]),
+                    Command::new("prefix").about("Find the inetnums / inet6nums and routes covering or covered by a prefix")
+                        .args([
+                            Arg::new("prefix").help("The target prefix (i.e. 172.20.0.0/24)").required(true)
+                        ]),
+                    Command::new("attr").about("Find all objects with an attribute value matching a regular expression")
+                        .args([
+                            Arg::new("key").help("The attribute key (i.e. nserver)").required(true),
+                            Arg::new("value_regex").help("Regular expression to match the attribute value against").required(true),
+                        ]),
+                ]).arg(
+                Arg::new("whois")
+                    .short('w')
+                    .long("whois")
+                    .action(ArgAction::SetTrue)
+                    .help("Output whois-style text instead of JSON")
+            ),
             Command::new("graph")
                 .about("Object output with forward and backlinks, path between objects, related objects (JSON / graphviz dot format)")
                 .subcommand_required(true)
@@ -165,10 +272,25 @@ pub fn get_arg_matches() -> ArgMatches {
                         .value_parser(clap::value_parser!(u16))
                         .default_value("8080")
                         .help("Port to listen on"),
+                    Arg::new("bind-ip")
+                        .long("bind")
+                        .help("IP address to bind to"),
                     Arg::new("disable-roa")
                         .help("Disable ROA API endpoint")
                         .long("disable-roa")
                         .action(ArgAction::SetTrue),
+                    Arg::new("base-path")
+                        .long("base-path")
+                        .default_value("/")
+                        .help("URL path to serve the explorer under (i.e. /explorer/ behind a reverse proxy)"),
+                    Arg::new("tls-cert")
+                        .long("tls-cert")
+                        .requires("tls-key")
+                        .help("PEM certificate chain file to serve HTTPS with"),
+                    Arg::new("tls-key")
+                        .long("tls-key")
+                        .requires("tls-cert")
+                        .help("PEM private key file for the certificate"),
                 ]),
             #[cfg(feature = "rtr-server")]
             Command::new("rtr")
@@ -199,6 +321,38 @@ pub fn get_arg_matches() -> ArgMatches {
                         .default_value("600")
                         .help("RTR retry timing"),
                 ]),
+            #[cfg(feature = "whois-server")]
+            Command::new("whois")
+                .about("Start whois server (RFC 3912) for registry objects")
+                .args([
+                    Arg::new("port")
+                        .long("port")
+                        .short('p')
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("43")
+                        .help("Port to listen on"),
+                    Arg::new("bind-ip")
+                        .long("bind")
+                        .help("IP address to bind to"),
+                ]),
+            #[cfg(feature = "daemon")]
+            Command::new("serve")
+                .about("Start the explorer, RTR, whois, DNS and metrics listeners configured in a file from one registry snapshot")
+                .args([
+                    Arg::new("metrics-port")
+                        .long("metrics-port")
+                        .value_parser(clap::value_parser!(u16))
+                        .help("Port to serve Prometheus metrics on (disabled if not set)"),
+                    Arg::new("metrics-bind-ip")
+                        .long("metrics-bind")
+                        .help("IP address to bind the metrics listener to"),
+                ]),
+            Command::new("config")
+                .about("Configuration file tools")
+                .subcommand_required(true)
+                .subcommands([
+                    Command::new("check").about("Validate the configuration file and the environment overrides"),
+                ]),
             Command::new("remove")
                 .about("Safely remove a list of registry objects along with all their dependencies")
                 .subcommand_required(true)
@@ -295,7 +449,7 @@ pub fn get_arg_matches() -> ArgMatches {
                         ),
                 ])
         ],
-    ).get_matches()
+    )
 }
 pub fn get_input_list(c: &ArgMatches) -> EitherOr<String, String> {
     if c.contains_id("list_file") {
@@ -306,3 +460,88 @@ pub fn get_input_list(c: &ArgMatches) -> EitherOr<String, String> {
         EitherOr::B(list.clone())
     }
 }
+
+fn resolver_format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .short('f')
+        .value_parser(["powerdns", "unbound", "knot", "bind", "dnsmasq"])
+        .default_value("powerdns")
+        .help("Resolver software to output the configuration for")
+}
+
+pub fn get_resolver_format(c: &ArgMatches) -> ResolverFormat {
+    match c.get_one::<String>("format").unwrap().as_str() {
+        "unbound" => ResolverFormat::Unbound,
+        "knot" => ResolverFormat::Knot,
+        "bind" => ResolverFormat::Bind,
+        "dnsmasq" => ResolverFormat::Dnsmasq,
+        _ => ResolverFormat::PowerDns,
+    }
+}
+
+#[cfg(feature = "explorer")]
+pub fn get_explorer_listen_options(c: &ArgMatches) -> ListenOptions {
+    let tls = match (c.get_one::<String>("tls-cert"), c.get_one::<String>("tls-key")) {
+        (Some(cert), Some(key)) => Some(TlsFiles { cert: cert.into(), key: key.into() }),
+        _ => None,
+    };
+    ListenOptions {
+        port: *c.get_one::<u16>("port").unwrap(),
+        bind_ip: c.get_one::<String>("bind-ip").cloned(),
+        base_path: c.get_one::<String>("base-path").unwrap().clone(),
+        tls,
+    }
+}
+
+/// Resolve the settings of each listener configured for `serve` the same way as for its standalone subcommand
+#[cfg(feature = "daemon")]
+pub fn get_serve_config(config: &Config, registry_root: &std::path::Path, c: &ArgMatches) -> BoxResult<ServeConfig> {
+    // Matches of each subcommand on the path, as the arguments of a parent command are not part of its subcommand's
+    let path_matches = |path: &[&str]| -> BoxResult<Option<Vec<ArgMatches>>> {
+        if !config.has_section(path) {
+            return Ok(None);
+        }
+        let args = [OsString::from(NAME), registry_root.as_os_str().to_owned()].into_iter()
+            .chain(path.iter().map(OsString::from));
+        let mut matches = config.apply(get_command()).try_get_matches_from(args)
+            .map_err(|e| format!("Invalid settings for {}: {}", path.join(" "), e))?;
+        let mut result = Vec::with_capacity(path.len());
+        for name in path {
+            matches = matches.subcommand_matches(name).unwrap().clone();
+            result.push(matches.clone());
+        }
+        Ok(Some(result))
+    };
+    let subcommand_matches = |path: &[&str]| -> BoxResult<Option<ArgMatches>> {
+        Ok(path_matches(path)?.and_then(|mut x| x.pop()))
+    };
+    let listener = |m: ArgMatches| Listener {
+        port: *m.get_one::<u16>("port").unwrap(),
+        bind: m.get_one::<String>("bind-ip").cloned(),
+    };
+
+    Ok(ServeConfig {
+        explorer: subcommand_matches(&["explorer"])?.map(|m| ExplorerListener {
+            listen: get_explorer_listen_options(&m),
+            roa: !*m.get_one::<bool>("disable-roa").unwrap(),
+        }),
+        rtr: subcommand_matches(&["rtr"])?.map(|m| RtrListener {
+            port: *m.get_one::<u16>("port").unwrap(),
+            bind: m.get_one::<String>("bind-ip").cloned(),
+            refresh: *m.get_one::<u32>("refresh").unwrap(),
+            retry: *m.get_one::<u32>("retry").unwrap(),
+            expire: *m.get_one::<u32>("expire").unwrap(),
+        }),
+        whois: subcommand_matches(&["whois"])?.map(listener),
+        dns: path_matches(&["dns", "serve"])?.map(|m| DnsListener {
+            port: *m[1].get_one::<u16>("port").unwrap(),
+            bind: m[1].get_one::<String>("bind-ip").cloned(),
+            strict: *m[0].get_one::<bool>("strict").unwrap(),
+        }),
+        metrics: c.get_one::<u16>("metrics-port").map(|port| Listener {
+            port: *port,
+            bind: c.get_one::<String>("metrics-bind-ip").cloned(),
+        }),
+    })
+}
@@ -1,5 +1,8 @@
 #![forbid(unsafe_code)]
+use crate::modules::object_finder::FinderQuery;
+use registry_core::traversal::RelatedFilter;
 use crate::modules::registry_remove::RemovalCategory;
+use crate::modules::zone_files::ResolverFormat;
 use crate::modules::util::BoxResult;
 use std::io;
 use std::io::Write;
@@ -14,7 +17,7 @@ const NAME: &str = env!("CARGO_PKG_NAME");
 
 
 fn main() {
-    let cmd = cmd::get_arg_matches();
+    let (cmd, config) = cmd::get_arg_matches();
     let base_path = cmd.get_one::<String>("registry_root").unwrap().to_owned();
     let base_path = PathBuf::from(base_path);
 
@@ -57,20 +60,44 @@ fn main() {
             }
         }
         Some(("dns", c)) => {
+            let is_strict = *c.get_one::<bool>("strict").unwrap();
             match c.subcommand() {
                 Some(("zones", d)) => {
                     let auth_servers: Vec<String> = d.get_many("authoritative_servers").unwrap().cloned().collect();
-                    output_result(modules::zone_files::output_forward_zones(&base_path, auth_servers));
+                    let format = cmd::get_resolver_format(d);
+                    output_result(modules::zone_files::output_forward_zones(&base_path, auth_servers, format, is_strict));
                 }
                 Some(("zones-legacy", d)) => {
                     let auth_servers: Vec<String> = d.get_many("authoritative_servers").unwrap().cloned().collect();
-                    output_result(modules::zone_files::output_forward_zones_legacy(&base_path, auth_servers));
+                    output_result(modules::zone_files::output_forward_zones_legacy(&base_path, auth_servers, is_strict));
                 }
-                Some(("tas", _)) => {
-                    output_result(modules::zone_files::output_tas(&base_path, false));
+                Some(("tas", d)) => {
+                    let format = cmd::get_resolver_format(d);
+                    output_result(modules::zone_files::output_tas(&base_path, format, false, is_strict));
                 }
                 Some(("tas-yml", _)) => {
-                    output_result(modules::zone_files::output_tas(&base_path, true));
+                    output_result(modules::zone_files::output_tas(&base_path, ResolverFormat::PowerDns, true, is_strict));
+                }
+                Some(("reverse", d)) => {
+                    let zones: Vec<String> = d.get_many("zones").map(|x| x.cloned().collect()).unwrap_or_default();
+                    let output_dir = d.get_one::<String>("output_dir").map(PathBuf::from);
+                    output_result(modules::reverse_zones::output(&base_path, zones, output_dir, is_strict));
+                }
+                #[cfg(feature = "dns-server")]
+                Some(("serve", d)) => {
+                    use crate::modules::dns_server::start_dns_server;
+                    let port = *d.get_one::<u16>("port").unwrap();
+                    let bind_ip = d.get_one::<String>("bind-ip").cloned();
+                    output_result(start_dns_server(&base_path, port, bind_ip, is_strict));
+                }
+                Some(("check", d)) => {
+                    let json = *d.get_one::<bool>("json").unwrap();
+                    output_result(modules::dns_check::output(&base_path, json, is_strict));
+                }
+                Some(("authoritative", d)) => {
+                    let zones: Vec<String> = d.get_many("zones").map(|x| x.cloned().collect()).unwrap_or_default();
+                    let output_dir = d.get_one::<String>("output_dir").map(PathBuf::from);
+                    output_result(modules::authoritative_zones::output(&base_path, zones, output_dir, is_strict));
                 }
                 _ => unreachable!()
             }
@@ -96,13 +123,32 @@ fn main() {
             output_result(result)
         }
         Some(("object_finder", c)) => {
-            match c.subcommand() {
+            let whois_format = *c.get_one::<bool>("whois").unwrap();
+            let query = match c.subcommand() {
                 Some(("ip", d)) => {
-                    let search_ip =  d.get_one::<String>("address").unwrap().clone();
-                    output_result(modules::object_finder::output(&base_path, &search_ip));
+                    FinderQuery::Ip(d.get_one::<String>("address").unwrap().clone())
+                }
+                Some(("asn", d)) => {
+                    FinderQuery::Asn(d.get_one::<String>("asn").unwrap().clone())
+                }
+                Some(("mnt", d)) => {
+                    FinderQuery::Mnt(d.get_one::<String>("mnt").unwrap().clone())
+                }
+                Some(("person", d)) => {
+                    FinderQuery::Person(d.get_one::<String>("nic_hdl").unwrap().clone())
+                }
+                Some(("prefix", d)) => {
+                    FinderQuery::Prefix(d.get_one::<String>("prefix").unwrap().clone())
+                }
+                Some(("attr", d)) => {
+                    FinderQuery::Attr {
+                        key: d.get_one::<String>("key").unwrap().clone(),
+                        value_pattern: d.get_one::<String>("value_regex").unwrap().clone(),
+                    }
                 }
                 _ => unreachable!()
-            }
+            };
+            output_result(modules::object_finder::output(&base_path, query, whois_format));
         },
         Some(("graph", c)) => {
             match c.subcommand() {
@@ -145,7 +191,14 @@ fn main() {
                     };
                     let graphviz = *c.get_one::<bool>("graphviz").unwrap();
                     let no_cross_dn42_mnt = *c.get_one::<bool>("no_cross_dn42_mnt").unwrap();
-                    let result = modules::registry_graph_tools::output_related(&base_path, obj_type, obj_name, enforce_mnt_by, related_mnt_by, not_contain_value, contain_value, graphviz, no_cross_dn42_mnt);
+                    let filter = RelatedFilter {
+                        enforce_mnt_by,
+                        only_related_to_mnt: related_mnt_by,
+                        not_contain_value,
+                        contain_value,
+                        no_cross_dn42_mnt,
+                    };
+                    let result = modules::registry_graph_tools::output_related(&base_path, obj_type, obj_name, filter, graphviz);
                     output_result(result)
                 }
                 Some(("path", c)) => {
@@ -174,9 +227,9 @@ fn main() {
         #[cfg(feature = "explorer")]
         Some(("explorer", c)) => {
             use crate::modules::explorer::start_explorer;
-            let port = *c.get_one::<u16>("port").unwrap();
+            let listen = cmd::get_explorer_listen_options(c);
             let disable_roa = *c.get_one::<bool>("disable-roa").unwrap();
-            let result = start_explorer(&base_path, port, !disable_roa);
+            let result = start_explorer(&base_path, listen, !disable_roa);
             output_result(result);
         }
         #[cfg(feature = "rtr-server")]
@@ -190,6 +243,27 @@ fn main() {
             let result = start_rtr(&base_path, port, bind_ip, refresh, retry, expire);
             output_result(result);
         }
+        #[cfg(feature = "whois-server")]
+        Some(("whois", c)) => {
+            use crate::modules::whois_server::start_whois;
+            let port = *c.get_one::<u16>("port").unwrap();
+            let bind_ip = c.get_one::<String>("bind-ip").cloned();
+            let result = start_whois(&base_path, port, bind_ip);
+            output_result(result);
+        }
+        #[cfg(feature = "daemon")]
+        Some(("serve", c)) => {
+            use crate::modules::daemon::start_serve;
+            let result = cmd::get_serve_config(&config, &base_path, c)
+                .and_then(|serve_config| start_serve(&base_path, serve_config));
+            output_result(result);
+        }
+        Some(("config", c)) => {
+            match c.subcommand() {
+                Some(("check", _)) => output_result(modules::config::output_check(&config)),
+                _ => unreachable!()
+            }
+        }
         Some(("remove", c)) => {
             let result = match c.subcommand() {
                 Some(("mnt", c)) => {
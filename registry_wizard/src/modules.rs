@@ -1,10 +1,12 @@
 pub mod zone_files;
+mod zone_builder;
+pub mod reverse_zones;
+pub mod authoritative_zones;
+pub mod dns_check;
 pub mod util;
-mod object_reader;
 pub mod hierarchical_prefixes;
 pub mod mrt_activity;
 pub mod object_metadata;
-mod registry_graph;
 pub mod registry_remove;
 mod registry_graphviz;
 pub mod inactive_asns;
@@ -13,4 +15,11 @@ pub mod registry_graph_tools;
 pub mod explorer;
 #[cfg(feature = "rtr-server")]
 pub mod rtr;
-pub mod object_finder;
\ No newline at end of file
+#[cfg(feature = "dns-server")]
+pub mod dns_server;
+#[cfg(feature = "whois-server")]
+pub mod whois_server;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+pub mod object_finder;
+pub mod config;
\ No newline at end of file
@@ -0,0 +1,96 @@
+use registry_core::object_reader::ObjectLine;
+use crate::modules::util::BoxResult;
+use crate::modules::zone_builder::{check_name, check_nserver_names, fqdn, is_subdomain, object_values, parse_ds_rdata_list, parse_nserver, read_zone_objects, report_invalid, write_zones, zone_serial, DsRecord, RecordData, Zone, ZoneObjects};
+use crate::modules::zone_files::decode_registry_sync_name;
+use std::path::{Path, PathBuf};
+
+pub fn output(registry_root: &Path, zone_filter: Vec<String>, output_dir: Option<PathBuf>, strict: bool) -> BoxResult<String> {
+    let dns = read_zone_objects(registry_root, "dns")?;
+    let zones = build_authoritative_zones(&ZoneObjects::new(zone_serial(registry_root), &dns, &[], &[]), strict)?;
+    write_zones(zones, &zone_filter, output_dir)
+}
+
+struct DomainObject {
+    domain: String,
+    n_server: Vec<String>,
+    ds_rdata: Vec<DsRecord>,
+}
+
+/// Build a zone for every registry TLD containing the delegations, glue and DS records of its domains.
+/// Invalid objects abort in strict mode and are skipped otherwise.
+pub(in crate::modules) fn build_authoritative_zones<T: ObjectLine>(objects: &ZoneObjects<T>, strict: bool) -> BoxResult<Vec<Zone>> {
+    let mut tld_objects = Vec::new();
+    let mut domain_objects = Vec::new();
+    for object in &objects.dns {
+        let domain = object_values(object, "domain").into_iter().next().unwrap_or_else(|| object.filename.clone());
+        let domain_object = DomainObject {
+            domain: fqdn(&domain),
+            n_server: object_values(object, "nserver"),
+            ds_rdata: parse_ds_rdata_list(&object_values(object, "ds-rdata"), &object.filename, strict)
+                .map_err(|e| format!("data/dns/{}: {}", object.filename, e))?,
+        };
+        if let Err(e) = check_name(&domain_object.domain).and_then(|_| check_nserver_names(&domain_object.n_server)) {
+            report_invalid(format!("data/dns/{}: {}", object.filename, e), strict)?;
+            continue;
+        }
+        if domain.contains('.') {
+            domain_objects.push(domain_object);
+            continue;
+        }
+        let is_dn42_mnt = object_values(object, "mnt-by").iter().any(|x| x == "DN42-MNT");
+        if !is_dn42_mnt {
+            report_invalid(format!("Skipping TLD '{}' as it is not maintained by DN42-MNT", object.filename), strict)?;
+            continue;
+        }
+        tld_objects.push(domain_object);
+    }
+    tld_objects.sort_by(|a, b| a.domain.cmp(&b.domain));
+    domain_objects.sort_by(|a, b| a.domain.cmp(&b.domain));
+
+    let mut zones = Vec::new();
+    for tld in &tld_objects {
+        let apex_nameservers: Vec<String> = tld.n_server.iter().map(|x| parse_nserver(x).0).collect();
+        if apex_nameservers.is_empty() {
+            report_invalid(format!("no nameservers found for TLD '{}', skipping", tld.domain), strict)?;
+            continue;
+        }
+        // The DS records of the TLD itself are trust anchors and not part of its zone
+        let mut zone = Zone::new(&tld.domain, objects.serial, &apex_nameservers);
+
+        for domain in &domain_objects {
+            if !is_subdomain(&domain.domain, &tld.domain) || domain.n_server.is_empty() {
+                continue;
+            }
+            // Domains below a delegated domain belong to the delegated zone
+            let is_occluded = domain_objects.iter().any(|other| {
+                other.domain != domain.domain && !other.n_server.is_empty() && is_subdomain(&domain.domain, &other.domain)
+            });
+            if is_occluded {
+                continue;
+            }
+            for n_server in &domain.n_server {
+                let (host, _) = parse_nserver(n_server);
+                if !host.is_empty() {
+                    zone.add(&domain.domain, RecordData::Ns(fqdn(&host)));
+                }
+            }
+            for ds in &domain.ds_rdata {
+                zone.add(&domain.domain, RecordData::Ds(ds.clone()));
+            }
+        }
+        zones.push(zone);
+    }
+
+    // Glue and registry-sync addresses end up in the zone that contains the nameserver name
+    for n_server in tld_objects.iter().chain(domain_objects.iter()).flat_map(|x| x.n_server.iter()) {
+        let (host, address) = parse_nserver(n_server);
+        let Some(address) = address.or_else(|| decode_registry_sync_name(&host)) else {
+            continue;
+        };
+        for zone in zones.iter_mut() {
+            zone.add_glue(&host, address);
+        }
+    }
+
+    Ok(zones)
+}
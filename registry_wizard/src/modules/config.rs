@@ -0,0 +1,246 @@
+use std::path::{Path, PathBuf};
+use clap::{Arg, Command};
+use toml::{Table, Value};
+use crate::modules::util::BoxResult;
+
+const ENV_PREFIX: &str = "REGISTRY_WIZARD";
+const CONFIG_ENV: &str = "REGISTRY_WIZARD_CONFIG";
+/// Arguments that can not be set from the configuration itself
+const IGNORED_ARGS: [&str; 3] = ["config", "help", "version"];
+
+/// Settings from the TOML configuration file and the environment. Each subcommand has a section
+/// (i.e. `[rtr]` or `[dns.serve]`) with keys named after the long option of the argument.
+#[derive(Default)]
+pub struct Config {
+    file: Option<PathBuf>,
+    table: Table,
+}
+
+impl Config {
+    /// Read the configuration file (if any), add the environment overrides and validate the result
+    pub fn load(file: Option<PathBuf>, command: &Command) -> BoxResult<Config> {
+        let mut table = if let Some(file) = &file {
+            let content = std::fs::read_to_string(file)
+                .map_err(|e| format!("Error reading configuration file {}: {}", file.display(), e))?;
+            content.parse::<Table>()
+                .map_err(|e| format!("Error parsing configuration file {}: {}", file.display(), e))?
+        } else {
+            Table::new()
+        };
+        add_env_overrides(command, &mut Vec::new(), &mut table);
+
+        let mut built = command.clone();
+        built.build();
+        let mut errors = Vec::new();
+        check_section(&table, &built, &mut Vec::new(), &mut errors);
+        if !errors.is_empty() {
+            let source = file.as_ref().map(|x| x.display().to_string()).unwrap_or(String::from("environment"));
+            return Err(format!("Invalid configuration ({}):\n  {}", source, errors.join("\n  ")).into());
+        }
+        Ok(Config { file, table })
+    }
+
+    /// Use the configured values as defaults of the arguments, so that command line flags take precedence
+    pub fn apply(&self, command: Command) -> Command {
+        apply_section(command, Some(&self.table))
+    }
+
+    /// Whether the configuration has a section for the subcommand path (i.e. `["dns", "serve"]`)
+    #[cfg(feature = "daemon")]
+    pub fn has_section(&self, path: &[&str]) -> bool {
+        section(&self.table, path).is_some()
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+}
+
+/// The configuration file given with `--config` or the `REGISTRY_WIZARD_CONFIG` environment variable.
+/// It is needed before the arguments can be parsed, as it provides their defaults.
+pub fn config_file_from_args() -> Option<PathBuf> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(file) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(file));
+        }
+    }
+    std::env::var_os(CONFIG_ENV).map(PathBuf::from)
+}
+
+pub fn output_check(config: &Config) -> BoxResult<String> {
+    let Some(file) = config.file() else {
+        return Err(format!("No configuration file specified (use --config or {})", CONFIG_ENV).into());
+    };
+    let mut sections = Vec::new();
+    list_sections(&config.table, &mut Vec::new(), &mut sections);
+    let mut output = format!("Configuration file {} is valid\n", file.display());
+    if !sections.is_empty() {
+        output += format!("Sections: {}\n", sections.join(", ")).as_str();
+    }
+    Ok(output)
+}
+
+#[cfg(feature = "daemon")]
+fn section<'a>(table: &'a Table, path: &[&str]) -> Option<&'a Table> {
+    path.iter().try_fold(table, |table, name| table.get(*name)?.as_table())
+}
+
+fn section_name(path: &[String]) -> String {
+    if path.is_empty() {
+        String::from("top level")
+    } else {
+        format!("[{}]", path.join("."))
+    }
+}
+
+fn list_sections(table: &Table, path: &mut Vec<String>, sections: &mut Vec<String>) {
+    for (key, value) in table {
+        if let Value::Table(sub_table) = value {
+            path.push(key.clone());
+            sections.push(section_name(path));
+            list_sections(sub_table, path, sections);
+            path.pop();
+        }
+    }
+}
+
+fn config_key(arg: &Arg) -> &str {
+    arg.get_long().unwrap_or(arg.get_id().as_str())
+}
+
+fn is_configurable(arg: &Arg) -> bool {
+    !IGNORED_ARGS.contains(&arg.get_id().as_str())
+}
+
+fn takes_multiple_values(arg: &Arg) -> bool {
+    arg.get_num_args().is_some_and(|x| x.max_values() > 1)
+}
+
+/// Environment variables are named after the subcommand path and key, i.e. `REGISTRY_WIZARD_DNS_SERVE_PORT`
+fn env_name(path: &[String], key: &str) -> String {
+    let mut name = String::from(ENV_PREFIX);
+    for part in path.iter().map(String::as_str).chain([key]) {
+        name.push('_');
+        name.extend(part.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' }));
+    }
+    name
+}
+
+fn add_env_overrides(command: &Command, path: &mut Vec<String>, table: &mut Table) {
+    for arg in command.get_arguments().filter(|x| is_configurable(x)) {
+        let Ok(env_value) = std::env::var(env_name(path, config_key(arg))) else {
+            continue;
+        };
+        let value = if takes_multiple_values(arg) {
+            Value::Array(env_value.split(',').map(|x| Value::String(x.trim().to_string())).collect())
+        } else {
+            Value::String(env_value)
+        };
+        let mut target = &mut *table;
+        for name in path.iter() {
+            let entry = target.entry(name.clone()).or_insert_with(|| Value::Table(Table::new()));
+            if !entry.is_table() {
+                *entry = Value::Table(Table::new());
+            }
+            target = entry.as_table_mut().unwrap();
+        }
+        target.insert(config_key(arg).to_string(), value);
+    }
+    for sub_command in command.get_subcommands() {
+        path.push(sub_command.get_name().to_string());
+        add_env_overrides(sub_command, path, table);
+        path.pop();
+    }
+}
+
+fn values_of(value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::String(x) => Ok(vec![x.clone()]),
+        Value::Integer(x) => Ok(vec![x.to_string()]),
+        Value::Float(x) => Ok(vec![x.to_string()]),
+        Value::Boolean(x) => Ok(vec![x.to_string()]),
+        Value::Array(list) => {
+            let mut result = Vec::with_capacity(list.len());
+            for item in list {
+                if item.is_array() {
+                    return Err(String::from("nested arrays are not supported"));
+                }
+                result.extend(values_of(item)?);
+            }
+            Ok(result)
+        }
+        Value::Datetime(_) | Value::Table(_) => Err(String::from("unsupported value type")),
+    }
+}
+
+fn check_section(table: &Table, command: &Command, path: &mut Vec<String>, errors: &mut Vec<String>) {
+    for (key, value) in table {
+        if let Value::Table(sub_table) = value
+            && let Some(sub_command) = command.find_subcommand(key) {
+            path.push(key.clone());
+            check_section(sub_table, sub_command, path, errors);
+            path.pop();
+            continue;
+        }
+        let Some(arg) = command.get_arguments().find(|x| is_configurable(x) && config_key(x) == key) else {
+            if value.is_table() {
+                path.push(key.clone());
+                errors.push(format!("unknown section {}", section_name(path)));
+                path.pop();
+            } else {
+                errors.push(format!("{}: unknown key '{}'", section_name(path), key));
+            }
+            continue;
+        };
+        let values = match values_of(value) {
+            Ok(values) => values,
+            Err(e) => {
+                errors.push(format!("{}: {}: {}", section_name(path), key, e));
+                continue;
+            }
+        };
+        if values.len() > 1 && !takes_multiple_values(arg) {
+            errors.push(format!("{}: {}: expected a single value", section_name(path), key));
+            continue;
+        }
+        // Let clap parse the values as defaults of the argument to run its value parser. The other
+        // arguments are kept (optional and without values) so that references like `requires` resolve.
+        let check_command = Command::new(command.get_name().to_string())
+            .no_binary_name(true)
+            .args(command.get_arguments().filter(|x| is_configurable(x)).map(|x| x.clone().required(false)))
+            .mut_arg(arg.get_id().clone(), |x| x.default_values(values));
+        if let Err(e) = check_command.try_get_matches_from(Vec::<String>::new()) {
+            let message = e.to_string();
+            let message = message.lines().next().unwrap_or_default().trim_start_matches("error: ");
+            errors.push(format!("{}: {}", section_name(path), message));
+        }
+    }
+}
+
+fn apply_section(mut command: Command, table: Option<&Table>) -> Command {
+    let Some(table) = table else {
+        return command;
+    };
+    let mut defaults = Vec::new();
+    for arg in command.get_arguments().filter(|x| is_configurable(x)) {
+        if let Some(values) = table.get(config_key(arg)).and_then(|x| values_of(x).ok()) {
+            defaults.push((arg.get_id().clone(), values));
+        }
+    }
+    for (id, values) in defaults {
+        command = command.mut_arg(id, |arg| arg.required(false).default_values(values));
+    }
+    let names: Vec<String> = command.get_subcommands().map(|x| x.get_name().to_string()).collect();
+    for name in names {
+        let sub_table = table.get(&name).and_then(|x| x.as_table());
+        command = command.mut_subcommand(&name, |sub_command| apply_section(sub_command, sub_table));
+    }
+    command
+}
@@ -0,0 +1,308 @@
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use rpki::rtr::payload::RouteOrigin;
+use rpki::rtr::server::NotifySender;
+use rpki::rtr::Timing;
+use tokio::sync::broadcast;
+
+use crate::modules::dns_server::{build_served_zones, ServedZone, ZoneData};
+use crate::modules::explorer::{build_app_state, roa_entries, AppState, DataStatus, ListenOptions, RoaFiles};
+use registry_core::registry_graph::{create_registry_graph, ServerGraph};
+use registry_core::schema::parse_registry_schema;
+use crate::modules::rtr::{route_origins, DataState, VrpSource};
+use crate::modules::util::os_signals::{signal_listener, CustomSignal};
+use crate::modules::util::{get_current_unix_time, get_git_commit_hash, print_warnings, BoxResult};
+use crate::modules::whois_server::{build_whois_data, WhoisData};
+use crate::modules::zone_builder::{zone_serial, ZoneObjects};
+
+/// Listeners of the `serve` command, resolved from the configuration sections of the standalone subcommands
+pub(crate) struct ServeConfig {
+    pub(crate) explorer: Option<ExplorerListener>,
+    pub(crate) rtr: Option<RtrListener>,
+    pub(crate) whois: Option<Listener>,
+    pub(crate) dns: Option<DnsListener>,
+    pub(crate) metrics: Option<Listener>,
+}
+
+pub(crate) struct Listener {
+    pub(crate) port: u16,
+    pub(crate) bind: Option<String>,
+}
+
+pub(crate) struct ExplorerListener {
+    pub(crate) listen: ListenOptions,
+    pub(crate) roa: bool,
+}
+
+pub(crate) struct RtrListener {
+    pub(crate) port: u16,
+    pub(crate) bind: Option<String>,
+    pub(crate) refresh: u32,
+    pub(crate) retry: u32,
+    pub(crate) expire: u32,
+}
+
+pub(crate) struct DnsListener {
+    pub(crate) port: u16,
+    pub(crate) bind: Option<String>,
+    /// Invalid objects fail the snapshot instead of being skipped
+    pub(crate) strict: bool,
+}
+
+/// Everything derived from one state of the registry
+struct Snapshot {
+    commit_hash: String,
+    object_counts: Vec<(String, usize)>,
+    explorer: Option<AppState>,
+    route_origins: Option<Vec<RouteOrigin>>,
+    whois: Option<WhoisData>,
+    zones: Option<Vec<ServedZone>>,
+}
+
+/// The data handles of the running listeners
+struct Services {
+    explorer: Option<Arc<RwLock<AppState>>>,
+    rtr: Option<(Arc<DataState>, NotifySender)>,
+    whois: Option<Arc<RwLock<Arc<WhoisData>>>>,
+    dns: Option<ZoneData>,
+    metrics: Arc<RwLock<Metrics>>,
+}
+
+#[derive(Default)]
+struct Metrics {
+    commit_hash: String,
+    snapshot_time: u64,
+    reloads: u64,
+    reload_errors: u64,
+    object_counts: Vec<(String, usize)>,
+    route_origins: Option<usize>,
+    zones: Option<usize>,
+}
+
+pub fn start_serve(registry_root: impl AsRef<Path>, config: ServeConfig) -> BoxResult<String> {
+    if config.explorer.is_none() && config.rtr.is_none() && config.whois.is_none() && config.dns.is_none() {
+        return Err("No listener configured, add at least one of the [explorer], [rtr], [whois] or [dns.serve] sections to the configuration".into());
+    }
+
+    let registry_root = registry_root.as_ref().to_path_buf();
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let snapshot = build_snapshot(&registry_root, &config)
+            .map_err(|err| format!("Error reading registry data: {}", err))?;
+
+        let mut services = Services {
+            explorer: config.explorer.as_ref().map(|_| Arc::new(RwLock::new(AppState::default()))),
+            rtr: config.rtr.as_ref().map(|_| (Arc::new(DataState::new()), NotifySender::new())),
+            whois: config.whois.as_ref().map(|_| Arc::new(RwLock::new(Arc::new(WhoisData::default())))),
+            dns: config.dns.as_ref().map(|_| Arc::new(RwLock::new(Arc::new(Vec::new())))),
+            metrics: Arc::new(RwLock::new(Metrics::default())),
+        };
+        publish_snapshot(&mut services, snapshot);
+
+        let (sig_chan_tx, mut sig_chan_rx) = broadcast::channel::<CustomSignal>(1);
+        let mut handles = vec![tokio::spawn(signal_listener(sig_chan_tx.clone()))];
+
+        if let (Some(listener), Some(app_state)) = (&config.explorer, &services.explorer) {
+            handles.push(tokio::spawn(crate::modules::explorer::start_server(app_state.clone(), listener.listen.clone(), sig_chan_tx.subscribe())));
+        }
+        if let (Some(listener), Some((data_state, notify))) = (&config.rtr, &services.rtr) {
+            let timing = Timing { refresh: listener.refresh, retry: listener.retry, expire: listener.expire };
+            let vrp_source = VrpSource::new(data_state.clone(), timing);
+            handles.push(tokio::spawn(crate::modules::rtr::server(notify.clone(), vrp_source, listener.port, listener.bind.clone(), sig_chan_tx.subscribe())));
+        }
+        if let (Some(listener), Some(whois_data)) = (&config.whois, &services.whois) {
+            handles.push(tokio::spawn(crate::modules::whois_server::server(whois_data.clone(), listener.port, listener.bind.clone(), sig_chan_tx.subscribe())));
+        }
+        if let (Some(listener), Some(zone_data)) = (&config.dns, &services.dns) {
+            handles.push(tokio::spawn(crate::modules::dns_server::server(zone_data.clone(), listener.port, listener.bind.clone(), sig_chan_tx.clone())));
+        }
+        if let Some(listener) = &config.metrics {
+            let addr = socket_addr(&listener.bind, listener.port)?;
+            handles.push(tokio::spawn(metrics_server(services.metrics.clone(), addr, sig_chan_tx.subscribe())));
+        }
+
+        handles.push(tokio::spawn(async move {
+            loop {
+                match sig_chan_rx.recv().await.unwrap() {
+                    CustomSignal::Shutdown => {
+                        break;
+                    }
+                    CustomSignal::DataUpdate => {
+                        eprintln!("Registry data update triggered");
+                        match build_snapshot(&registry_root, &config) {
+                            Err(err) => {
+                                eprintln!("Error updating registry data, keeping the previous snapshot: {}", err);
+                                services.metrics.write().unwrap().reload_errors += 1;
+                                if let Some(app_state) = &services.explorer {
+                                    app_state.write().unwrap().status = DataStatus::ReloadFailed(err.to_string());
+                                }
+                            }
+                            Ok(snapshot) => {
+                                publish_snapshot(&mut services, snapshot);
+                                services.metrics.write().unwrap().reloads += 1;
+                            }
+                        }
+                        eprintln!("Registry data update completed")
+                    }
+                }
+            }
+            Ok(())
+        }));
+
+        let result = futures_util::future::try_join_all(handles.into_iter().map(|handle| async { handle.await? })).await;
+        if let Err(e) = result {
+            return Err(format!("Error: {}", e));
+        }
+        Ok(())
+    })?;
+    Ok("".into())
+}
+
+fn socket_addr(bind: &Option<String>, port: u16) -> Result<SocketAddr, String> {
+    let bind_ip = if let Some(bind_ip) = bind {
+        IpAddr::from_str(bind_ip.as_str()).map_err(|e| format!("Invalid bind address '{}': {}", bind_ip, e))?
+    } else {
+        IpAddr::from(Ipv6Addr::UNSPECIFIED)
+    };
+    Ok(SocketAddr::from((bind_ip, port)))
+}
+
+/// Parse the registry once and derive the data of every configured listener from it
+fn build_snapshot(registry_root: &PathBuf, config: &ServeConfig) -> BoxResult<Snapshot> {
+    let schema = Arc::new(print_warnings(parse_registry_schema(registry_root, false)?));
+    // Built once and shared by the explorer, whois and DNS data
+    let graph: Arc<ServerGraph> = Arc::new(print_warnings(create_registry_graph(registry_root, &schema, true, true)?));
+    let commit_hash = get_git_commit_hash(registry_root).unwrap_or(String::from("N/A"));
+
+    let mut object_counts: Vec<(String, usize)> = graph.categories().iter().map(|(c, x)| (c.clone(), x.len())).collect();
+    object_counts.sort();
+
+    let explorer_roa = config.explorer.as_ref().is_some_and(|x| x.roa);
+    let mut roa_files = None;
+    let mut origins = None;
+    // Like in the standalone explorer, a failed ROA generation does not stop the update. The explorer answers the
+    // ROA endpoints with 503 and RTR keeps serving the previous data.
+    let roa_data = (explorer_roa || config.rtr.is_some()).then(|| {
+        roa_wizard::get_roa_data_v4v6(registry_root, |warn| {
+            eprintln!("Warning during ROA data generation: {}", warn);
+            roa_wizard::WarningAction::ActionContinue
+        }).map_err(|err| eprintln!("Error generating ROA data: {}", err)).ok()
+    }).flatten();
+    if let Some((mut v4, v6)) = roa_data {
+        let roa4 = explorer_roa.then(|| v4.output_bird(registry_root));
+        let roa6 = explorer_roa.then(|| v6.output_bird(registry_root));
+        v4.merge(v6);
+        if config.rtr.is_some() {
+            origins = route_origins(&v4).map_err(|err| eprintln!("Error generating RTR data: {}", err)).ok();
+        }
+        if let (Some(roa4), Some(roa6)) = (roa4, roa6) {
+            let roas = roa_entries(&v4);
+            roa_files = Some(RoaFiles { v4: roa4, v6: roa6, json: v4.output_json(), roas });
+        }
+    }
+
+    let explorer = config.explorer.as_ref()
+        .map(|x| build_app_state(graph.clone(), schema.clone(), registry_root, commit_hash.clone(), roa_files, !x.roa));
+    let whois = config.whois.as_ref()
+        .map(|_| build_whois_data(graph.clone(), commit_hash.clone()));
+    let zones = match &config.dns {
+        Some(listener) => Some(build_served_zones(&ZoneObjects::from_graph(&graph, zone_serial(registry_root)), listener.strict)?),
+        None => None,
+    };
+
+    Ok(Snapshot { commit_hash, object_counts, explorer, route_origins: origins, whois, zones })
+}
+
+/// Swap the data of every listener to the new snapshot
+fn publish_snapshot(services: &mut Services, snapshot: Snapshot) {
+    let mut metrics = services.metrics.write().unwrap();
+    metrics.commit_hash = snapshot.commit_hash;
+    metrics.snapshot_time = get_current_unix_time();
+    metrics.object_counts = snapshot.object_counts;
+
+    if let (Some(handle), Some(app_state)) = (&services.explorer, snapshot.explorer) {
+        *handle.write().unwrap() = app_state;
+    }
+    if let (Some((data_state, notify)), Some(origins)) = (&mut services.rtr, snapshot.route_origins) {
+        metrics.route_origins = Some(origins.len());
+        data_state.add_data(origins);
+        notify.notify();
+    }
+    if let (Some(handle), Some(whois_data)) = (&services.whois, snapshot.whois) {
+        *handle.write().unwrap() = Arc::new(whois_data);
+    }
+    if let (Some(handle), Some(zones)) = (&services.dns, snapshot.zones) {
+        metrics.zones = Some(zones.len());
+        *handle.write().unwrap() = Arc::new(zones);
+    }
+    eprintln!("Serving registry data from commit {}", metrics.commit_hash);
+}
+
+async fn metrics_server(metrics: Arc<RwLock<Metrics>>, addr: SocketAddr, mut sig_chan_rx: broadcast::Receiver<CustomSignal>) -> BoxResult<()> {
+    let listener = tokio::net::TcpListener::bind(&addr).await
+        .map_err(|x| format!("Error listening on TCP: {}", x))?;
+    eprintln!("Metrics server listening on {}. Endpoint: '/metrics'", addr);
+
+    let app = Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics);
+
+    axum::serve(listener, app).with_graceful_shutdown(async move {
+        loop {
+            match sig_chan_rx.recv().await.unwrap() {
+                CustomSignal::Shutdown => { break }
+                CustomSignal::DataUpdate => {}
+            }
+        }
+    }).await
+        .map_err(|e| format!("Error starting server: {}", e))?;
+    Ok(())
+}
+
+async fn metrics_handler(State(metrics): State<Arc<RwLock<Metrics>>>) -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", "text/plain; version=0.0.4".parse().unwrap());
+    (headers, format_metrics(&metrics.read().unwrap()))
+}
+
+/// Prometheus text exposition format
+fn format_metrics(metrics: &Metrics) -> String {
+    let mut output = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+        let _ = writeln!(output, "# HELP registry_wizard_{} {}", name, help);
+        let _ = writeln!(output, "# TYPE registry_wizard_{} {}", name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(output, "registry_wizard_{}{} {}", name, labels, value);
+        }
+    };
+
+    metric("info", "gauge", "Registry commit of the served snapshot",
+           &[(format!("{{commit=\"{}\"}}", metrics.commit_hash), String::from("1"))]);
+    metric("snapshot_timestamp_seconds", "gauge", "Unix time the served snapshot was built",
+           &[(String::new(), metrics.snapshot_time.to_string())]);
+    metric("reloads_total", "counter", "Successful registry data reloads",
+           &[(String::new(), metrics.reloads.to_string())]);
+    metric("reload_errors_total", "counter", "Failed registry data reloads",
+           &[(String::new(), metrics.reload_errors.to_string())]);
+    let objects: Vec<(String, String)> = metrics.object_counts.iter()
+        .map(|(category, count)| (format!("{{category=\"{}\"}}", category), count.to_string()))
+        .collect();
+    metric("objects", "gauge", "Registry objects per category", &objects);
+    if let Some(route_origins) = metrics.route_origins {
+        metric("route_origins", "gauge", "Route origins served over RTR",
+               &[(String::new(), route_origins.to_string())]);
+    }
+    if let Some(zones) = metrics.zones {
+        metric("dns_zones", "gauge", "Zones served over DNS",
+               &[(String::new(), zones.to_string())]);
+    }
+    output
+}
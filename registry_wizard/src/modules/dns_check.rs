@@ -0,0 +1,151 @@
+use registry_core::object_reader::{read_registry_objects, KeyValue, SimpleObjectLine};
+use crate::modules::util::BoxResult;
+use crate::modules::zone_builder::{is_subdomain, DsRecord};
+use crate::modules::zone_files::decode_registry_sync_name;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Serialize, Copy, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+struct Finding {
+    object: String,
+    severity: Severity,
+    message: String,
+}
+
+/// Output the findings. In strict mode the report is written to stdout and an error is returned if errors were found.
+pub fn output(registry_root: &Path, json: bool, strict: bool) -> BoxResult<String> {
+    let (checked_count, findings) = check_dns_objects(registry_root)?;
+    let error_count = findings.iter().filter(|x| x.severity == Severity::Error).count();
+    let output = if json {
+        serde_json::to_string(&findings)? + "\n"
+    } else {
+        format_report(checked_count, error_count, &findings)
+    };
+    if strict && error_count > 0 {
+        io::stdout().write_all(output.as_bytes())?;
+        return Err(format!("{} error(s) found in dns objects", error_count).into());
+    }
+    Ok(output)
+}
+
+fn format_report(checked_count: usize, error_count: usize, findings: &[Finding]) -> String {
+    let mut output = String::new();
+    for finding in findings {
+        let severity = match finding.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        output += format!("{}: {}: {}\n", severity, finding.object, finding.message).as_str();
+    }
+    output += format!("Checked {} object(s): {} error(s), {} warning(s)\n",
+                      checked_count, error_count, findings.len() - error_count).as_str();
+    output
+}
+
+fn check_dns_objects(registry_root: &Path) -> BoxResult<(usize, Vec<Finding>)> {
+    let mut checked_count = 0;
+    let mut findings = Vec::new();
+    for (dir_name, is_domain) in [("dns", true), ("inetnum", false), ("inet6num", false)] {
+        let mut objects = read_registry_objects::<SimpleObjectLine>(registry_root, &Path::new("data").join(dir_name), false)?;
+        objects.sort_by(|a, b| a.filename.cmp(&b.filename));
+        for object in objects {
+            if !is_domain && object.key_value.get("nserver").is_none() && object.key_value.get("ds-rdata").is_none() {
+                continue;
+            }
+            checked_count += 1;
+            let name = format!("data/{}/{}", dir_name, object.filename);
+            let domain = if is_domain {
+                Some(object.key_value.get("domain").and_then(|x| x.first()).unwrap_or(&object.filename).as_str())
+            } else {
+                None
+            };
+            for (severity, message) in check_object(&object.key_value, domain) {
+                findings.push(Finding { object: name.clone(), severity, message });
+            }
+        }
+    }
+    Ok((checked_count, findings))
+}
+
+fn check_object(key_value: &KeyValue<SimpleObjectLine>, domain: Option<&str>) -> Vec<(Severity, String)> {
+    let mut findings = Vec::new();
+    let empty = Vec::new();
+    let n_servers = key_value.get("nserver").unwrap_or(&empty);
+    let ds_rdata = key_value.get("ds-rdata").unwrap_or(&empty);
+
+    for ds in ds_rdata {
+        if let Err(e) = DsRecord::from_str(ds) {
+            findings.push((Severity::Error, format!("invalid ds-rdata '{}': {}", ds, e)));
+        }
+    }
+    if !ds_rdata.is_empty() && n_servers.is_empty() {
+        findings.push((Severity::Warning, String::from("ds-rdata present without any nserver")));
+    }
+
+    let mut seen = HashSet::new();
+    let mut hosts = Vec::new();
+    let mut hosts_with_address = HashSet::new();
+    for n_server in n_servers {
+        let fields: Vec<&str> = n_server.split_whitespace().collect();
+        if !seen.insert(fields.join(" ").to_lowercase()) {
+            findings.push((Severity::Warning, format!("duplicate nserver '{}'", n_server)));
+            continue;
+        }
+        let Some(host) = fields.first() else {
+            findings.push((Severity::Error, String::from("empty nserver")));
+            continue;
+        };
+        let host = host.trim_end_matches('.').to_lowercase();
+        if fields.len() > 2 {
+            findings.push((Severity::Error, format!("invalid nserver '{}': too many fields", n_server)));
+        }
+        if !is_valid_hostname(&host) {
+            findings.push((Severity::Error, format!("invalid nameserver name '{}'", host)));
+        }
+        if host.ends_with(".registry-sync.dn42") && decode_registry_sync_name(&host).is_none() {
+            findings.push((Severity::Error, format!("invalid registry-sync address encoding '{}'", host)));
+        }
+        if let Some(address) = fields.get(1) {
+            if IpAddr::from_str(address).is_ok() {
+                hosts_with_address.insert(host.clone());
+            } else {
+                findings.push((Severity::Error, format!("invalid nameserver address '{}' for '{}'", address, host)));
+            }
+        }
+        if !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+
+    if let Some(domain) = domain {
+        for host in hosts {
+            if is_subdomain(&host, domain) && !hosts_with_address.contains(&host) {
+                findings.push((Severity::Error, format!("missing glue address for in-zone nameserver '{}'", host)));
+            }
+        }
+    }
+    findings
+}
+
+fn is_valid_hostname(host: &str) -> bool {
+    if host.is_empty() || host.len() > 253 {
+        return false;
+    }
+    host.split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63
+            && !label.starts_with('-') && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    })
+}
@@ -0,0 +1,794 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::broadcast;
+
+use crate::modules::authoritative_zones::build_authoritative_zones;
+use crate::modules::reverse_zones::build_reverse_zones;
+use crate::modules::util::os_signals::{signal_listener, CustomSignal};
+use crate::modules::util::BoxResult;
+use crate::modules::zone_builder::{fqdn, is_subdomain, read_zone_objects, zone_serial, RecordData, ResourceRecord, Zone, ZoneObjects};
+use registry_core::object_reader::ObjectLine;
+
+const TYPE_A: u16 = 1;
+const TYPE_NS: u16 = 2;
+const TYPE_CNAME: u16 = 5;
+const TYPE_SOA: u16 = 6;
+const TYPE_AAAA: u16 = 28;
+const TYPE_OPT: u16 = 41;
+const TYPE_DS: u16 = 43;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+
+const RCODE_NO_ERROR: u8 = 0;
+const RCODE_FORMAT_ERROR: u8 = 1;
+const RCODE_NX_DOMAIN: u8 = 3;
+const RCODE_NOT_IMPLEMENTED: u8 = 4;
+const RCODE_REFUSED: u8 = 5;
+
+const HEADER_LENGTH: usize = 12;
+const MAX_UDP_SIZE: usize = 512;
+/// Largest message that can be framed with the two byte length prefix used over TCP
+const MAX_TCP_SIZE: usize = u16::MAX as usize;
+/// Largest UDP payload advertised via EDNS (DNS flag day 2020 recommendation)
+const MAX_EDNS_UDP_SIZE: u16 = 1232;
+const MAX_POINTER_JUMPS: usize = 16;
+const MAX_NAME_LENGTH: usize = 255;
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A zone with its records grouped by owner name. Empty non-terminals are present with no records.
+pub(in crate::modules) struct ServedZone {
+    origin: String,
+    nodes: HashMap<String, Vec<ResourceRecord>>,
+}
+
+impl ServedZone {
+    fn new(zone: Zone) -> Self {
+        let mut nodes: HashMap<String, Vec<ResourceRecord>> = HashMap::new();
+        for record in zone.records {
+            let mut current = record.name.clone();
+            while let Some(parent) = parent_name(&current) {
+                if parent == zone.origin || !is_subdomain(parent, &zone.origin) {
+                    break;
+                }
+                nodes.entry(parent.to_string()).or_default();
+                current = parent.to_string();
+            }
+            nodes.entry(record.name.clone()).or_default().push(record);
+        }
+        ServedZone { origin: zone.origin, nodes }
+    }
+
+    fn records(&self, name: &str, record_type: u16) -> Vec<ResourceRecord> {
+        self.nodes.get(name)
+            .map(|records| records.iter().filter(|r| record_type_code(&r.data) == record_type).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The A and AAAA records for nameservers inside this zone
+    fn glue(&self, ns_records: &[ResourceRecord]) -> Vec<ResourceRecord> {
+        let mut glue = Vec::new();
+        for record in ns_records {
+            if let RecordData::Ns(host) = &record.data {
+                glue.extend(self.records(host, TYPE_A));
+                glue.extend(self.records(host, TYPE_AAAA));
+            }
+        }
+        glue
+    }
+}
+
+pub(in crate::modules) type ZoneData = Arc<RwLock<Arc<Vec<ServedZone>>>>;
+
+struct Question {
+    name: String,
+    record_type: u16,
+    class: u16,
+}
+
+struct Response {
+    rcode: u8,
+    authoritative: bool,
+    answer: Vec<ResourceRecord>,
+    authority: Vec<ResourceRecord>,
+    additional: Vec<ResourceRecord>,
+}
+
+impl Response {
+    fn new(rcode: u8, authoritative: bool) -> Self {
+        Response { rcode, authoritative, answer: Vec::new(), authority: Vec::new(), additional: Vec::new() }
+    }
+}
+
+pub fn start_dns_server(registry_root: impl AsRef<Path>, port: u16, bind_ip: Option<String>, strict: bool) -> BoxResult<String> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let zone_data: ZoneData = Arc::new(RwLock::new(Arc::new(Vec::new())));
+        let registry_root = registry_root.as_ref().to_path_buf();
+        update_zone_data(&registry_root, &zone_data, strict);
+
+        let (sig_chan_tx, mut sig_chan_rx) = broadcast::channel::<CustomSignal>(1);
+        let signal_listener_handle = tokio::spawn(signal_listener(sig_chan_tx.clone()));
+
+        let updater_zone_data = zone_data.clone();
+        let registry_data_updater = tokio::spawn(async move {
+            loop {
+                match sig_chan_rx.recv().await.unwrap() {
+                    CustomSignal::Shutdown => {
+                        break;
+                    }
+                    CustomSignal::DataUpdate => {
+                        eprintln!("Registry data update triggered");
+                        update_zone_data(&registry_root, &updater_zone_data, strict);
+                        eprintln!("Registry data update completed")
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        let server = tokio::spawn(server(zone_data, port, bind_ip, sig_chan_tx));
+        let result = tokio::try_join!(
+            async { registry_data_updater.await? },
+            async { server.await? },
+            async { signal_listener_handle.await? }
+        );
+        if let Err(e) = result {
+            return Err(format!("Error: {}", e));
+        }
+        Ok(())
+    })?;
+    Ok("".into())
+}
+
+/// Bind the UDP and TCP listeners and answer queries until shutdown
+pub(in crate::modules) async fn server(
+    zone_data: ZoneData,
+    port: u16,
+    bind_ip: Option<String>,
+    sig_chan_tx: broadcast::Sender<CustomSignal>,
+) -> BoxResult<()> {
+    let bind_ip = if let Some(bind_ip) = bind_ip {
+        IpAddr::from_str(bind_ip.as_str())?
+    } else {
+        IpAddr::from(Ipv6Addr::UNSPECIFIED)
+    };
+    let addr = SocketAddr::from((bind_ip, port));
+    let udp_socket = UdpSocket::bind(&addr).await.map_err(|e| format!("Error binding UDP {}: {}", addr, e))?;
+    let tcp_listener = TcpListener::bind(&addr).await.map_err(|e| format!("Error binding TCP {}: {}", addr, e))?;
+    println!(
+        "DNS server listening on {} (UDP and TCP). Send the POSIX 'SIGUSR1' signal to this process to trigger data update",
+        addr
+    );
+
+    let udp_server = tokio::spawn(udp_server(udp_socket, zone_data.clone(), sig_chan_tx.subscribe()));
+    let tcp_server = tokio::spawn(tcp_server(tcp_listener, zone_data, sig_chan_tx.subscribe()));
+    tokio::try_join!(
+        async { udp_server.await? },
+        async { tcp_server.await? }
+    )?;
+    Ok(())
+}
+
+/// Build the authoritative and reverse zones. In strict mode an invalid object fails the update.
+pub(in crate::modules) fn build_served_zones<T: ObjectLine>(objects: &ZoneObjects<T>, strict: bool) -> BoxResult<Vec<ServedZone>> {
+    let mut zones = build_authoritative_zones(objects, strict)?;
+    zones.extend(build_reverse_zones(objects, strict)?);
+    Ok(zones.into_iter().map(ServedZone::new).collect())
+}
+
+fn read_served_zones(registry_root: &Path, strict: bool) -> BoxResult<Vec<ServedZone>> {
+    let dns = read_zone_objects(registry_root, "dns")?;
+    let inetnum = read_zone_objects(registry_root, "inetnum")?;
+    let inet6num = read_zone_objects(registry_root, "inet6num")?;
+    build_served_zones(&ZoneObjects::new(zone_serial(registry_root), &dns, &inetnum, &inet6num), strict)
+}
+
+fn update_zone_data(registry_root: &Path, zone_data: &ZoneData, strict: bool) {
+    match read_served_zones(registry_root, strict) {
+        Err(err) => {
+            eprintln!("Error updating registry data: {}", err);
+        }
+        Ok(zones) => {
+            eprintln!("Serving {} zones", zones.len());
+            *zone_data.write().unwrap() = Arc::new(zones);
+        }
+    }
+}
+
+fn current_zones(zone_data: &ZoneData) -> Arc<Vec<ServedZone>> {
+    zone_data.read().unwrap().clone()
+}
+
+async fn udp_server(
+    socket: UdpSocket,
+    zone_data: ZoneData,
+    mut signal_rx: broadcast::Receiver<CustomSignal>,
+) -> BoxResult<()> {
+    let mut buffer = [0u8; 4096];
+    loop {
+        tokio::select! {
+            result = socket.recv_from(&mut buffer) => {
+                let (length, peer) = match result {
+                    Ok(x) => x,
+                    Err(e) => {
+                        eprintln!("Error receiving UDP packet: {}", e);
+                        continue;
+                    }
+                };
+                if let Some(response) = handle_packet(&buffer[..length], &current_zones(&zone_data), true)
+                    && let Err(e) = socket.send_to(&response, peer).await {
+                    eprintln!("Error sending UDP response to {}: {}", peer, e);
+                }
+            }
+            signal = signal_rx.recv() => {
+                if let Ok(CustomSignal::Shutdown) = signal {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn tcp_server(
+    listener: TcpListener,
+    zone_data: ZoneData,
+    mut signal_rx: broadcast::Receiver<CustomSignal>,
+) -> BoxResult<()> {
+    loop {
+        tokio::select! {
+            result = listener.accept() => {
+                match result {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_tcp_connection(stream, zone_data.clone()));
+                    }
+                    Err(e) => eprintln!("Error accepting TCP connection: {}", e),
+                }
+            }
+            signal = signal_rx.recv() => {
+                if let Ok(CustomSignal::Shutdown) = signal {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Answer length-prefixed messages (RFC 1035 4.2.2) until the client closes the connection or goes idle
+async fn handle_tcp_connection(mut stream: TcpStream, zone_data: ZoneData) {
+    loop {
+        let Ok(Ok(length)) = tokio::time::timeout(TCP_IDLE_TIMEOUT, stream.read_u16()).await else {
+            return;
+        };
+        let mut message = vec![0u8; length as usize];
+        if !matches!(tokio::time::timeout(TCP_IDLE_TIMEOUT, stream.read_exact(&mut message)).await, Ok(Ok(_))) {
+            return;
+        }
+        let Some(response) = handle_packet(&message, &current_zones(&zone_data), false) else {
+            return;
+        };
+        // Responses are truncated to MAX_TCP_SIZE, a longer one cannot be framed
+        let Ok(length) = u16::try_from(response.len()) else {
+            eprintln!("Closing TCP connection, response of {} bytes exceeds the message size limit", response.len());
+            return;
+        };
+        let mut framed = Vec::with_capacity(response.len() + 2);
+        framed.extend(length.to_be_bytes());
+        framed.extend(response);
+        if stream.write_all(&framed).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Parse a query and build the response message. Returns None for packets that should not be answered.
+fn handle_packet(packet: &[u8], zones: &[ServedZone], is_udp: bool) -> Option<Vec<u8>> {
+    if packet.len() < HEADER_LENGTH {
+        return None;
+    }
+    let id = read_u16(packet, 0)?;
+    let flags = read_u16(packet, 2)?;
+    if flags & 0x8000 != 0 {
+        // Not a query
+        return None;
+    }
+    let opcode = ((flags >> 11) & 0x0F) as u8;
+    let recursion_desired = flags & 0x0100 != 0;
+    let header = (id, opcode, recursion_desired);
+
+    if opcode != 0 {
+        return Some(encode_response(header, None, &Response::new(RCODE_NOT_IMPLEMENTED, false), None, MAX_UDP_SIZE));
+    }
+    let (question, edns_payload_size) = match parse_query(packet) {
+        Some(x) => x,
+        None => return Some(encode_response(header, None, &Response::new(RCODE_FORMAT_ERROR, false), None, MAX_UDP_SIZE)),
+    };
+
+    let response = if question.class != CLASS_IN {
+        Response::new(RCODE_REFUSED, false)
+    } else {
+        resolve(zones, &question.name, question.record_type)
+    };
+    let max_size = match (is_udp, edns_payload_size) {
+        (false, _) => MAX_TCP_SIZE,
+        (true, Some(size)) => (size.min(MAX_EDNS_UDP_SIZE) as usize).max(MAX_UDP_SIZE),
+        (true, None) => MAX_UDP_SIZE,
+    };
+    Some(encode_response(header, Some(&question), &response, edns_payload_size, max_size))
+}
+
+/// Returns the question and the EDNS UDP payload size (if the query contains an OPT record)
+fn parse_query(packet: &[u8]) -> Option<(Question, Option<u16>)> {
+    let question_count = read_u16(packet, 4)?;
+    let answer_count = read_u16(packet, 6)?;
+    let authority_count = read_u16(packet, 8)?;
+    let additional_count = read_u16(packet, 10)?;
+    if question_count != 1 {
+        return None;
+    }
+    let (name, offset) = read_name(packet, HEADER_LENGTH)?;
+    let question = Question { name, record_type: read_u16(packet, offset)?, class: read_u16(packet, offset + 2)? };
+
+    let mut offset = offset + 4;
+    let mut edns_payload_size = None;
+    for i in 0..(answer_count as usize + authority_count as usize + additional_count as usize) {
+        let (_, name_end) = read_name(packet, offset)?;
+        let record_type = read_u16(packet, name_end)?;
+        let class = read_u16(packet, name_end + 2)?;
+        let rdata_length = read_u16(packet, name_end + 8)? as usize;
+        if record_type == TYPE_OPT && i >= (answer_count + authority_count) as usize {
+            edns_payload_size = Some(class);
+        }
+        offset = name_end + 10 + rdata_length;
+    }
+    Some((question, edns_payload_size))
+}
+
+/// Look up the answer for a query in the zone closest to the queried name
+fn resolve(zones: &[ServedZone], query_name: &str, record_type: u16) -> Response {
+    let name = fqdn(query_name);
+    let Some(zone) = zones.iter()
+        .filter(|z| is_subdomain(&name, &z.origin))
+        .max_by_key(|z| z.origin.len()) else {
+        return Response::new(RCODE_REFUSED, false);
+    };
+
+    // Names at or below a zone cut are answered with a referral, except for the DS records at the cut itself
+    for cut in names_below_origin(&name, &zone.origin) {
+        let ns_records = zone.records(&cut, TYPE_NS);
+        if ns_records.is_empty() {
+            continue;
+        }
+        if cut == name && record_type == TYPE_DS {
+            let ds_records = zone.records(&cut, TYPE_DS);
+            let mut response = Response::new(RCODE_NO_ERROR, true);
+            if ds_records.is_empty() {
+                response.authority = zone.records(&zone.origin, TYPE_SOA);
+            }
+            response.answer = ds_records;
+            return response;
+        }
+        let mut response = Response::new(RCODE_NO_ERROR, false);
+        response.additional = zone.glue(&ns_records);
+        response.authority = ns_records;
+        return response;
+    }
+
+    let Some(records) = zone.nodes.get(&name) else {
+        let mut response = Response::new(RCODE_NX_DOMAIN, true);
+        response.authority = zone.records(&zone.origin, TYPE_SOA);
+        return response;
+    };
+    let mut response = Response::new(RCODE_NO_ERROR, true);
+    let matching: Vec<ResourceRecord> = records.iter()
+        .filter(|r| record_type == TYPE_ANY || record_type_code(&r.data) == record_type)
+        .cloned()
+        .collect();
+    if !matching.is_empty() {
+        if record_type == TYPE_NS {
+            response.additional = zone.glue(&matching);
+        }
+        response.answer = matching;
+        return response;
+    }
+
+    let cname = records.iter().find(|r| matches!(r.data, RecordData::Cname(_)));
+    if let Some(cname) = cname {
+        response.answer.push(cname.clone());
+        if let RecordData::Cname(target) = &cname.data
+            && is_subdomain(target, &zone.origin) {
+            let target_response = resolve(zones, target, record_type);
+            response.answer.extend(target_response.answer);
+            response.authority = target_response.authority;
+            response.additional = target_response.additional;
+        }
+        return response;
+    }
+
+    response.authority = zone.records(&zone.origin, TYPE_SOA);
+    response
+}
+
+/// The names between the zone origin (exclusive) and the name (inclusive), closest to the origin first
+fn names_below_origin(name: &str, origin: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut current = name;
+    while current != origin {
+        names.push(current.to_string());
+        match parent_name(current) {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    names.reverse();
+    names
+}
+
+fn parent_name(name: &str) -> Option<&str> {
+    match name.split_once('.') {
+        Some((_, "")) => Some("."),
+        Some((_, parent)) => Some(parent),
+        None => None,
+    }
+}
+
+fn encode_response(
+    header: (u16, u8, bool),
+    question: Option<&Question>,
+    response: &Response,
+    edns_payload_size: Option<u16>,
+    max_size: usize,
+) -> Vec<u8> {
+    let (id, opcode, recursion_desired) = header;
+    let encode = |truncated: bool| {
+        let mut flags: u16 = 0x8000 | ((opcode as u16) << 11) | response.rcode as u16;
+        if response.authoritative {
+            flags |= 0x0400;
+        }
+        if truncated {
+            flags |= 0x0200;
+        }
+        if recursion_desired {
+            flags |= 0x0100;
+        }
+        let sections: [&[ResourceRecord]; 3] = if truncated {
+            [&[], &[], &[]]
+        } else {
+            [&response.answer, &response.authority, &response.additional]
+        };
+
+        let mut buffer = Vec::with_capacity(MAX_UDP_SIZE);
+        buffer.extend(id.to_be_bytes());
+        buffer.extend(flags.to_be_bytes());
+        buffer.extend((question.is_some() as u16).to_be_bytes());
+        buffer.extend((sections[0].len() as u16).to_be_bytes());
+        buffer.extend((sections[1].len() as u16).to_be_bytes());
+        buffer.extend((sections[2].len() as u16 + edns_payload_size.is_some() as u16).to_be_bytes());
+        if let Some(question) = question {
+            write_name(&mut buffer, &question.name);
+            buffer.extend(question.record_type.to_be_bytes());
+            buffer.extend(question.class.to_be_bytes());
+        }
+        for record in sections.iter().flat_map(|x| x.iter()) {
+            write_record(&mut buffer, record);
+        }
+        if edns_payload_size.is_some() {
+            // OPT pseudo-record: root name, type, UDP payload size, extended rcode and flags, no options
+            buffer.push(0);
+            buffer.extend(TYPE_OPT.to_be_bytes());
+            buffer.extend(MAX_EDNS_UDP_SIZE.to_be_bytes());
+            buffer.extend(0u32.to_be_bytes());
+            buffer.extend(0u16.to_be_bytes());
+        }
+        buffer
+    };
+
+    let buffer = encode(false);
+    if buffer.len() > max_size {
+        encode(true)
+    } else {
+        buffer
+    }
+}
+
+fn write_record(buffer: &mut Vec<u8>, record: &ResourceRecord) {
+    write_name(buffer, &record.name);
+    buffer.extend(record_type_code(&record.data).to_be_bytes());
+    buffer.extend(CLASS_IN.to_be_bytes());
+    buffer.extend(record.ttl.to_be_bytes());
+
+    let mut rdata = Vec::new();
+    match &record.data {
+        RecordData::Soa { mname, rname, serial, refresh, retry, expire, minimum } => {
+            write_name(&mut rdata, mname);
+            write_name(&mut rdata, rname);
+            for value in [serial, refresh, retry, expire, minimum] {
+                rdata.extend(value.to_be_bytes());
+            }
+        }
+        RecordData::Ns(host) | RecordData::Cname(host) => write_name(&mut rdata, host),
+        RecordData::A(v4) => rdata.extend(v4.octets()),
+        RecordData::Aaaa(v6) => rdata.extend(v6.octets()),
+        RecordData::Ds(ds) => {
+            rdata.extend(ds.key_tag.to_be_bytes());
+            rdata.push(ds.algorithm);
+            rdata.push(ds.digest_type);
+            // The digest has been validated as hexadecimal with an even length
+            for i in (0..ds.digest.len()).step_by(2) {
+                rdata.push(u8::from_str_radix(&ds.digest[i..i + 2], 16).unwrap_or_default());
+            }
+        }
+    }
+    buffer.extend((rdata.len() as u16).to_be_bytes());
+    buffer.extend(rdata);
+}
+
+/// Write an uncompressed name. Zone records are checked to have valid names when the zone is built and query names
+/// consist of labels read from the wire, so all labels fit into the 63 byte limit.
+fn write_name(buffer: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|x| !x.is_empty()) {
+        let label = label.as_bytes();
+        buffer.push(label.len() as u8);
+        buffer.extend(label);
+    }
+    buffer.push(0);
+}
+
+/// Read a (possibly compressed) name, returning it with a trailing dot and the offset following it. Labels that are not
+/// ASCII or contain dots are rejected, as no zone has such names and they could not be written back unchanged.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+    let mut name_length = 1;
+    loop {
+        let length = *packet.get(offset)? as usize;
+        match length & 0xC0 {
+            0xC0 => {
+                jumps += 1;
+                if jumps > MAX_POINTER_JUMPS {
+                    return None;
+                }
+                end.get_or_insert(offset + 2);
+                offset = ((length & 0x3F) << 8) | *packet.get(offset + 1)? as usize;
+            }
+            0x00 if length == 0 => {
+                let name = labels.join(".") + ".";
+                return Some((name, end.unwrap_or(offset + 1)));
+            }
+            0x00 => {
+                let label = packet.get(offset + 1..offset + 1 + length)?;
+                name_length += 1 + length;
+                if name_length > MAX_NAME_LENGTH || !label.is_ascii() || label.contains(&b'.') {
+                    return None;
+                }
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset += 1 + length;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn read_u16(packet: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(offset)?, *packet.get(offset + 1)?]))
+}
+
+fn record_type_code(data: &RecordData) -> u16 {
+    match data {
+        RecordData::Soa { .. } => TYPE_SOA,
+        RecordData::Ns(_) => TYPE_NS,
+        RecordData::A(_) => TYPE_A,
+        RecordData::Aaaa(_) => TYPE_AAAA,
+        RecordData::Cname(_) => TYPE_CNAME,
+        RecordData::Ds(_) => TYPE_DS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const ID: u16 = 0x1234;
+
+    fn test_zones() -> Vec<ServedZone> {
+        let mut zone = Zone::new("dn42", 1, &[String::from("ns1.dn42")]);
+        zone.add_glue("ns1.dn42", IpAddr::V4(Ipv4Addr::new(172, 20, 0, 53)));
+        zone.add("foo.dn42", RecordData::Ns(String::from("ns.foo.dn42.")));
+        zone.add_glue("ns.foo.dn42", IpAddr::V4(Ipv4Addr::new(172, 20, 1, 1)));
+        for i in 0..5000u32 {
+            zone.add("big.dn42", RecordData::A(Ipv4Addr::from(0xAC140000 + i)));
+        }
+        vec![ServedZone::new(zone)]
+    }
+
+    fn encode_name(name: &str) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        write_name(&mut buffer, name);
+        buffer
+    }
+
+    /// Build a query message, optionally with an OPT record advertising the UDP payload size
+    fn query(name: &str, record_type: u16, edns_payload_size: Option<u16>) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.extend(ID.to_be_bytes());
+        packet.extend(0x0100u16.to_be_bytes());
+        packet.extend(1u16.to_be_bytes());
+        packet.extend(0u16.to_be_bytes());
+        packet.extend(0u16.to_be_bytes());
+        packet.extend((edns_payload_size.is_some() as u16).to_be_bytes());
+        packet.extend(encode_name(name));
+        packet.extend(record_type.to_be_bytes());
+        packet.extend(CLASS_IN.to_be_bytes());
+        if let Some(size) = edns_payload_size {
+            packet.push(0);
+            packet.extend(TYPE_OPT.to_be_bytes());
+            packet.extend(size.to_be_bytes());
+            packet.extend(0u32.to_be_bytes());
+            packet.extend(0u16.to_be_bytes());
+        }
+        packet
+    }
+
+    fn flags(response: &[u8]) -> u16 {
+        read_u16(response, 2).unwrap()
+    }
+
+    fn rcode(response: &[u8]) -> u8 {
+        (flags(response) & 0x0F) as u8
+    }
+
+    /// Question, answer, authority and additional record counts
+    fn counts(response: &[u8]) -> [u16; 4] {
+        [4, 6, 8, 10].map(|offset| read_u16(response, offset).unwrap())
+    }
+
+    #[test]
+    fn header_of_authoritative_answer() {
+        let response = handle_packet(&query("ns1.dn42", TYPE_A, None), &test_zones(), true).unwrap();
+        assert_eq!(read_u16(&response, 0), Some(ID));
+        // Response, authoritative, recursion desired copied from the query
+        assert_eq!(flags(&response) & 0x8000, 0x8000);
+        assert_eq!(flags(&response) & 0x0400, 0x0400);
+        assert_eq!(flags(&response) & 0x0100, 0x0100);
+        assert_eq!(rcode(&response), RCODE_NO_ERROR);
+        assert_eq!(counts(&response), [1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn question_is_parsed_and_echoed() {
+        let packet = query("NS1.dn42", TYPE_AAAA, None);
+        let (question, edns_payload_size) = parse_query(&packet).unwrap();
+        assert_eq!(question.name, "NS1.dn42.");
+        assert_eq!(question.record_type, TYPE_AAAA);
+        assert_eq!(question.class, CLASS_IN);
+        assert_eq!(edns_payload_size, None);
+
+        let response = handle_packet(&packet, &test_zones(), true).unwrap();
+        let (name, offset) = read_name(&response, HEADER_LENGTH).unwrap();
+        assert_eq!(name, "NS1.dn42.");
+        assert_eq!(read_u16(&response, offset), Some(TYPE_AAAA));
+    }
+
+    #[test]
+    fn compressed_names() {
+        // 'dn42.' at offset 0, 'foo' followed by a pointer to it at offset 6
+        let mut packet = encode_name("dn42");
+        packet.extend([3, b'f', b'o', b'o', 0xC0, 0x00]);
+        assert_eq!(read_name(&packet, 0), Some((String::from("dn42."), 6)));
+        assert_eq!(read_name(&packet, 6), Some((String::from("foo.dn42."), 12)));
+
+        // A pointer to itself and a pointer past the end of the packet
+        assert_eq!(read_name(&[0xC0, 0x00], 0), None);
+        assert_eq!(read_name(&[0xC0, 0x10], 0), None);
+        // Reserved label types
+        assert_eq!(read_name(&[0x40, 0x00], 0), None);
+        assert_eq!(read_name(&[0x80, 0x00], 0), None);
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        // Label longer than the packet, missing terminating label, non-ASCII and dotted labels
+        assert_eq!(read_name(&[5, b'a', b'b'], 0), None);
+        assert_eq!(read_name(&[1, b'a'], 0), None);
+        assert_eq!(read_name(&[1, 0xFF, 0], 0), None);
+        assert_eq!(read_name(&[3, b'a', b'.', b'b', 0], 0), None);
+        // Names longer than 255 bytes
+        let mut packet = Vec::new();
+        for _ in 0..5 {
+            packet.push(63);
+            packet.extend([b'a'; 63]);
+        }
+        packet.push(0);
+        assert_eq!(read_name(&packet, 0), None);
+    }
+
+    #[test]
+    fn malformed_packets() {
+        let zones = test_zones();
+        // Shorter than the header and responses are not answered
+        assert_eq!(handle_packet(&[0; 11], &zones, true), None);
+        let mut packet = query("dn42", TYPE_SOA, None);
+        packet[2] |= 0x80;
+        assert_eq!(handle_packet(&packet, &zones, true), None);
+
+        // Missing question, cut off question, trailing records missing
+        let mut packet = query("dn42", TYPE_SOA, None);
+        packet[5] = 0;
+        assert_eq!(rcode(&handle_packet(&packet, &zones, true).unwrap()), RCODE_FORMAT_ERROR);
+        let packet = query("dn42", TYPE_SOA, None);
+        assert_eq!(rcode(&handle_packet(&packet[..packet.len() - 3], &zones, true).unwrap()), RCODE_FORMAT_ERROR);
+        let packet = query("dn42", TYPE_SOA, Some(4096));
+        assert_eq!(rcode(&handle_packet(&packet[..packet.len() - 5], &zones, true).unwrap()), RCODE_FORMAT_ERROR);
+
+        // Other opcodes and classes
+        let mut packet = query("dn42", TYPE_SOA, None);
+        packet[2] |= 0x10;
+        assert_eq!(rcode(&handle_packet(&packet, &zones, true).unwrap()), RCODE_NOT_IMPLEMENTED);
+        let mut packet = query("dn42", TYPE_SOA, None);
+        let class_offset = packet.len() - 1;
+        packet[class_offset] = 3;
+        assert_eq!(rcode(&handle_packet(&packet, &zones, true).unwrap()), RCODE_REFUSED);
+    }
+
+    #[test]
+    fn referrals_and_negative_answers() {
+        let zones = test_zones();
+        let response = handle_packet(&query("www.foo.dn42", TYPE_A, None), &zones, true).unwrap();
+        assert_eq!(flags(&response) & 0x0400, 0);
+        assert_eq!(rcode(&response), RCODE_NO_ERROR);
+        assert_eq!(counts(&response), [1, 0, 1, 1]);
+
+        let response = handle_packet(&query("missing.dn42", TYPE_A, None), &zones, true).unwrap();
+        assert_eq!(rcode(&response), RCODE_NX_DOMAIN);
+        assert_eq!(counts(&response), [1, 0, 1, 0]);
+
+        let response = handle_packet(&query("example.com", TYPE_A, None), &zones, true).unwrap();
+        assert_eq!(rcode(&response), RCODE_REFUSED);
+    }
+
+    #[test]
+    fn edns_and_truncation() {
+        let zones = test_zones();
+        // The OPT record is answered with one, keeping the advertised size within the limit
+        let response = handle_packet(&query("ns1.dn42", TYPE_A, Some(4096)), &zones, true).unwrap();
+        assert_eq!(counts(&response), [1, 1, 0, 1]);
+        let opt_offset = response.len() - 11;
+        assert_eq!(response[opt_offset], 0);
+        assert_eq!(read_u16(&response, opt_offset + 1), Some(TYPE_OPT));
+        assert_eq!(read_u16(&response, opt_offset + 3), Some(MAX_EDNS_UDP_SIZE));
+
+        // Too large for UDP without and with EDNS: truncated without records
+        for edns_payload_size in [None, Some(4096)] {
+            let response = handle_packet(&query("big.dn42", TYPE_A, edns_payload_size), &zones, true).unwrap();
+            assert_eq!(flags(&response) & 0x0200, 0x0200);
+            assert_eq!(counts(&response)[1..3], [0, 0]);
+            assert!(response.len() <= MAX_EDNS_UDP_SIZE as usize);
+        }
+
+        // Too large for TCP: truncated to fit into the length prefix
+        let response = handle_packet(&query("big.dn42", TYPE_A, None), &zones, false).unwrap();
+        assert_eq!(flags(&response) & 0x0200, 0x0200);
+        assert!(response.len() <= MAX_TCP_SIZE);
+        let response = handle_packet(&query("ns1.dn42", TYPE_A, None), &zones, false).unwrap();
+        assert_eq!(flags(&response) & 0x0200, 0);
+        assert_eq!(counts(&response), [1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn records_with_long_labels_are_skipped() {
+        let mut zone = Zone::new("dn42", 1, &[String::from("ns1.dn42")]);
+        let long_label = "a".repeat(64);
+        zone.add(&format!("{}.dn42", long_label), RecordData::A(Ipv4Addr::LOCALHOST));
+        zone.add("foo.dn42", RecordData::Ns(format!("{}.foo.dn42", long_label)));
+        assert_eq!(zone.records.len(), 2);
+    }
+}
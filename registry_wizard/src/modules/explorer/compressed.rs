@@ -0,0 +1,88 @@
+use std::io::Write;
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_WINDOW: u32 = 22;
+
+/// Response body stored once along with its gzip and brotli compressed forms, so that large
+/// responses do not need to be copied or compressed on every request
+#[derive(Clone)]
+pub(in crate::modules) struct PrecompressedBody {
+    raw: Bytes,
+    gzip: Bytes,
+    brotli: Bytes,
+    /// Quoted ETag of the body, given by the creator as bodies may contain their generation time
+    pub etag: String,
+}
+
+impl PrecompressedBody {
+    pub fn new(content: String, etag: String) -> Self {
+        let raw = Bytes::from(content);
+
+        let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::best());
+        let gzip = gzip.write_all(&raw).and_then(|_| gzip.finish()).unwrap_or_default();
+
+        let mut brotli = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut brotli, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+            let _ = writer.write_all(&raw);
+        }
+
+        PrecompressedBody {
+            etag,
+            raw,
+            gzip: Bytes::from(gzip),
+            brotli: Bytes::from(brotli),
+        }
+    }
+
+    /// Respond with the best encoding accepted by the client, or with 304 if its cached copy is current
+    pub fn response(&self, request_headers: &HeaderMap, content_type: &'static str, cache_control: &'static str) -> Response {
+        let client_etag = request_headers.get(header::IF_NONE_MATCH)
+            .and_then(|v| v.to_str().ok()).unwrap_or_default();
+        if client_etag == self.etag {
+            return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+        headers.insert(header::ETAG, HeaderValue::from_str(&self.etag).unwrap());
+        headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        let accepted = request_headers.get(header::ACCEPT_ENCODING)
+            .and_then(|v| v.to_str().ok()).unwrap_or_default();
+        let body = if !self.brotli.is_empty() && accepts_encoding(accepted, "br") {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+            self.brotli.clone()
+        } else if !self.gzip.is_empty() && accepts_encoding(accepted, "gzip") {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            self.gzip.clone()
+        } else {
+            self.raw.clone()
+        };
+        (headers, body).into_response()
+    }
+}
+
+/// Whether the `Accept-Encoding` header value lists the encoding without `q=0`
+fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|x| {
+        let mut parts = x.split(';').map(str::trim);
+        parts.next().is_some_and(|x| x.eq_ignore_ascii_case(encoding))
+            && !parts.any(|x| x.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0))
+    })
+}
+
+/// Hex encoded SHA-256 hash
+pub(in crate::modules) fn content_hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Quoted ETag header value of a hash
+pub(in crate::modules) fn etag(hash: &str) -> String {
+    format!("\"{}\"", hash)
+}
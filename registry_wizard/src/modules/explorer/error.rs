@@ -0,0 +1,63 @@
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use crate::modules::explorer::state::AppState;
+
+/// Seconds clients are asked to wait before retrying while the registry data is unavailable
+const RETRY_AFTER: &str = "30";
+
+/// Error response of the API, with a JSON `{"error": ..., "detail": ...}` body
+pub(super) enum ApiError {
+    /// Missing or malformed request parameters
+    BadRequest(String),
+    /// The requested category, object or data does not exist
+    NotFound(String),
+    /// The registry data is being loaded or the last reload failed
+    Unavailable(String),
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn bad_request(detail: impl Into<String>) -> Self {
+        ApiError::BadRequest(detail.into())
+    }
+
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        ApiError::NotFound(detail.into())
+    }
+
+    pub fn unavailable(detail: impl Into<String>) -> Self {
+        ApiError::Unavailable(detail.into())
+    }
+
+    pub fn internal(detail: impl Into<String>) -> Self {
+        ApiError::Internal(detail.into())
+    }
+
+    /// Fails with `Unavailable` unless the registry data is loaded and current
+    pub fn check_available(app_state: &AppState) -> Result<(), ApiError> {
+        match app_state.status.unavailable_reason() {
+            Some(reason) => Err(ApiError::Unavailable(reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error, detail) = match self {
+            ApiError::BadRequest(detail) => (StatusCode::BAD_REQUEST, "bad_request", detail),
+            ApiError::NotFound(detail) => (StatusCode::NOT_FOUND, "not_found", detail),
+            ApiError::Unavailable(detail) => (StatusCode::SERVICE_UNAVAILABLE, "unavailable", detail),
+            ApiError::Internal(detail) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", detail),
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from_static(RETRY_AFTER));
+        }
+        let body = json!({ "error": error, "detail": detail });
+        (status, headers, body.to_string()).into_response()
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::IntoResponse;
+use serde::Serialize;
+use crate::modules::explorer::error::ApiError;
+use crate::modules::explorer::state::AppState;
+use registry_core::object_reader::{OrderedObjectLine, RegistryObject};
+use registry_core::registry_graph::{NodeId, ServerGraph};
+use registry_core::traversal::{related_objects, shortest_path, stops_traversal, RelatedFilter};
+
+#[derive(Serialize)]
+struct GraphNode<'a> {
+    id: String,
+    category: &'a str,
+    name: &'a str,
+    /// The object the graph was requested for
+    root: bool,
+    /// Maintained by the mntner of the filter
+    highlight: bool,
+}
+
+#[derive(Serialize)]
+struct GraphEdge<'a> {
+    source: String,
+    target: String,
+    /// The attribute of the source object containing the link
+    key: &'a str,
+}
+
+#[derive(Serialize)]
+struct Graph<'a> {
+    nodes: Vec<GraphNode<'a>>,
+    edges: Vec<GraphEdge<'a>>,
+}
+
+#[derive(Serialize)]
+struct PathHop<'a> {
+    id: String,
+    category: &'a str,
+    name: &'a str,
+    /// The attribute linking the previous hop and this one (none for the first hop)
+    key: Option<&'a str>,
+    /// Whether this object references the previous hop instead of the other way around
+    reverse: bool,
+}
+
+/// The related objects subgraph of an object, using the same filters as `graph related`
+pub(super) async fn related_handler(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+    let (Some(object_type), Some(object_name)) = (params.get("type"), params.get("name")) else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+    let param = |key: &str| params.get(key).filter(|x| !x.is_empty()).cloned();
+    let filter = RelatedFilter {
+        enforce_mnt_by: param("enforce_mnt_by"),
+        only_related_to_mnt: param("related_mnt_by"),
+        not_contain_value: param("not_contain_value"),
+        contain_value: param("contain_value"),
+        no_cross_dn42_mnt: params.get("no_cross_dn42_mnt").is_some_and(|x| x == "true" || x == "1"),
+    };
+
+    let u = u.read().unwrap();
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    if u.etag == client_etag {
+        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+    }
+    let Some(root) = u.find_object(object_type, object_name) else {
+        return ApiError::not_found("object not found").into_response();
+    };
+    let registry = &u.graph;
+
+    let visited = related_objects(registry, root, &filter);
+
+    let included: HashSet<NodeId> = visited.iter().copied().collect();
+    let highlighted_mnt = filter.highlighted_mnt();
+    let mut graph = Graph { nodes: Vec::with_capacity(visited.len()), edges: Vec::new() };
+    let mut seen_edges = HashSet::new();
+    for &id in &visited {
+        let obj = registry.node(id);
+        let path = registry.path(id);
+        seen_edges.clear();
+        let highlight = highlighted_mnt.as_ref().is_some_and(|mnt| {
+            obj.object.key_value.get("mnt-by").is_some_and(|x| x.iter().any(|(_, v)| v == mnt))
+        });
+        for (line_number, target) in obj.get_forward_links() {
+            if !included.contains(target) || *target == id {
+                continue;
+            }
+            let key = line_key(&obj.object, *line_number).unwrap_or_default();
+            if seen_edges.insert((*target, key)) {
+                graph.edges.push(GraphEdge { source: path.clone(), target: registry.path(*target), key });
+            }
+        }
+        graph.nodes.push(GraphNode {
+            root: id == root,
+            id: path,
+            category: &obj.schema_ref,
+            name: &obj.object.filename,
+            highlight,
+        });
+    }
+
+    let Ok(js) = serde_json::to_string(&graph) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=1800, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, js).into_response()
+}
+
+/// Shortest path between two objects (following links in both directions), with the linking attribute of each hop
+pub(super) async fn path_handler(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+    let (Some(src), Some(dst)) = (params.get("src"), params.get("dst")) else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+
+    let u = u.read().unwrap();
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    if u.etag == client_etag {
+        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+    }
+    let (Some(src), Some(dst)) = (find_by_path(&u, src), find_by_path(&u, dst)) else {
+        return ApiError::not_found("object not found").into_response();
+    };
+    let registry = &u.graph;
+
+    // Paths starting or ending at objects whose links are not followed are not searched, as in the CLI
+    if stops_traversal(registry, src) || stops_traversal(registry, dst) {
+        return ApiError::not_found("A path between the specified objects was not found").into_response();
+    }
+    let Some(path) = shortest_path(registry, src, dst) else {
+        return ApiError::not_found("A path between the specified objects was not found").into_response();
+    };
+
+    let mut hops: Vec<PathHop> = Vec::with_capacity(path.len());
+    let mut previous: Option<NodeId> = None;
+    for &id in &path {
+        let obj = registry.node(id);
+        let (key, reverse) = match previous {
+            None => (None, false),
+            Some(prev) => match link_key(registry, prev, id) {
+                Some(key) => (Some(key), false),
+                None => (link_key(registry, id, prev), true),
+            }
+        };
+        hops.push(PathHop { id: registry.path(id), category: &obj.schema_ref, name: &obj.object.filename, key, reverse });
+        previous = Some(id);
+    }
+
+    let Ok(js) = serde_json::to_string(&hops) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=1800, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, js).into_response()
+}
+
+/// Object referred to by `category/name`, where the name may be any alias accepted by `AppState::find_object`
+fn find_by_path(app_state: &AppState, path: &str) -> Option<NodeId> {
+    let (category, name) = path.split_once('/')?;
+    app_state.find_object(category, name)
+}
+
+/// The attribute of the object on the given line
+pub(super) fn line_key(object: &RegistryObject<OrderedObjectLine>, line_number: usize) -> Option<&str> {
+    object.key_value.0.iter()
+        .find(|(_, lines)| lines.iter().any(|(n, _)| *n == line_number))
+        .map(|(key, _)| key.as_str())
+}
+
+/// The attribute of the object referencing the target
+fn link_key(graph: &ServerGraph, object: NodeId, target: NodeId) -> Option<&str> {
+    let object = graph.node(object);
+    object.get_forward_links().iter()
+        .find(|(_, x)| *x == target)
+        .and_then(|(line_number, _)| line_key(&object.object, *line_number))
+}
@@ -1,56 +1,162 @@
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
-use axum::extract::{Query, State};
+use axum::extract::{OriginalUri, Path, Query, State};
 use axum::http::{header, HeaderMap, HeaderValue, StatusCode, Uri};
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Redirect};
+use serde::Serialize;
 use crate::modules::explorer::{static_files, AppState};
+use crate::modules::explorer::error::ApiError;
+use crate::modules::explorer::state::WebRegistryObject;
 
-pub(super) async fn root_handler(request_headers: HeaderMap, uri: Uri) -> impl IntoResponse {
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+const ROA_CACHE_CONTROL: &str = "max-age=3600, public, stale-if-error=86400";
+
+pub(super) async fn root_handler(request_headers: HeaderMap, uri: Uri, OriginalUri(original_uri): OriginalUri) -> impl IntoResponse {
     let mut path = uri.path().trim_start_matches('/').to_owned();
     if path.is_empty() {
+        // The page uses relative URLs, so the base path must end with a slash
+        if !original_uri.path().ends_with('/') {
+            return Redirect::permanent(&format!("{}/", original_uri.path())).into_response();
+        }
         path = String::from("index.html");
     }
-    static_files::StaticFile { path, if_none_match: request_headers.get(header::IF_NONE_MATCH).cloned()}
+    static_files::StaticFile { path, if_none_match: request_headers.get(header::IF_NONE_MATCH).cloned()}.into_response()
+}
+
+/// The page at the base path with a trailing slash, which the nested router does not match
+pub(super) async fn base_path_handler(request_headers: HeaderMap) -> impl IntoResponse {
+    static_files::StaticFile { path: "index.html", if_none_match: request_headers.get(header::IF_NONE_MATCH).cloned()}
 }
 
 pub(super) async fn index_handler(request_headers: HeaderMap, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
     let client_etag = request_headers.get("if-none-match")
         .and_then(|v| v.to_str().ok()).unwrap_or_default();
     let u = u.read().unwrap();
-    let etag = u.etag.clone();
-    if etag == client_etag {
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    if u.etag == client_etag {
         return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
     }
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, u.index_json.clone()).into_response()
+}
 
-    let mut m  = serde_json::map::Map::new();
-    m.insert("commit".to_string(), serde_json::to_value(&u.commit_hash).unwrap_or_default());
-    m.insert("roa".to_string(), serde_json::to_value(!u.roa_disabled).unwrap_or_default());
-    m.insert("time".to_string(), serde_json::to_value(&u.generation_time).unwrap_or_default());
-    let info_val = serde_json::to_value(&m).unwrap_or_default();
-    let jr = serde_json::to_value(&u.index);
-    if jr.is_err() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response();
+/// Registry information along with the number of objects in each category
+pub(super) async fn info_handler(request_headers: HeaderMap, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let u = u.read().unwrap();
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
     }
-    let mut outer_map = serde_json::map::Map::with_capacity(2);
-    outer_map.insert("i".to_string(), info_val);
-    outer_map.insert("d".to_string(), jr.unwrap());
-    let r = serde_json::to_string(&outer_map);
-    drop(u);
+    if u.etag == client_etag {
+        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+    }
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, u.info_json.clone()).into_response()
+}
+
+#[derive(Serialize)]
+struct IndexPage<T: Serialize> {
+    /// Number of matching names
+    total: usize,
+    offset: usize,
+    items: Vec<T>,
+}
+
+/// `offset` and `limit` query parameters
+fn page_params(params: &HashMap<String, String>) -> Option<(usize, usize)> {
+    let offset = params.get("offset").map(|x| x.parse::<usize>()).transpose().ok()?.unwrap_or(0);
+    let limit = params.get("limit").map(|x| x.parse::<usize>()).transpose().ok()?.unwrap_or(DEFAULT_PAGE_SIZE);
+    Some((offset, limit.min(MAX_PAGE_SIZE)))
+}
+
+/// Object names of a category, optionally only those starting with a (case-insensitive) prefix
+pub(super) async fn category_index_handler(request_headers: HeaderMap, Path(category): Path<String>, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let Some((offset, limit)) = page_params(&params) else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+    let prefix = params.get("prefix").map(|x| x.to_uppercase()).unwrap_or_default();
 
-    if r.is_err() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response();
+    let u = u.read().unwrap();
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
     }
+    if u.etag == client_etag {
+        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+    }
+    let Some(entries) = u.index.get(&category) else {
+        return ApiError::not_found("category not found").into_response();
+    };
+    // The entries are sorted by key, so the matches are a contiguous range
+    let start = entries.partition_point(|x| x.key.as_str() < prefix.as_str());
+    let end = start + entries[start..].partition_point(|x| x.key.starts_with(&prefix));
+    let matches = &entries[start..end];
+    let page = IndexPage {
+        total: matches.len(),
+        offset,
+        items: matches.iter().skip(offset).take(limit).map(|x| x.name.as_str()).collect(),
+    };
+
+    let Ok(js) = serde_json::to_string(&page) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", HeaderValue::from_static("application/json"));
     headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, must-revalidate"));
-    headers.insert("ETag", HeaderValue::from_str(&etag).unwrap());
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, js).into_response()
+}
 
+/// Objects whose name contains the (case-insensitive) query, optionally limited to one category
+pub(super) async fn search_handler(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let (Some(query), Some((offset, limit))) = (params.get("query"), page_params(&params)) else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+    let query = query.to_uppercase();
+    let category = params.get("category").filter(|x| !x.is_empty());
 
-    if let Ok(r) = r {
-        (headers, r).into_response()
-    } else {
-        (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response()
+    let u = u.read().unwrap();
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    if u.etag == client_etag {
+        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+    }
+    let mut categories: Vec<&String> = u.index.keys()
+        .filter(|x| category.is_none_or(|c| c == *x))
+        .collect();
+    categories.sort_unstable();
+    let mut page = IndexPage { total: 0, offset, items: Vec::new() };
+    for category in categories {
+        for entry in u.index[category].iter().filter(|x| x.key.contains(&query)) {
+            if page.total >= offset && page.items.len() < limit {
+                page.items.push((category.as_str(), entry.name.as_str()));
+            }
+            page.total += 1;
+        }
     }
+
+    let Ok(js) = serde_json::to_string(&page) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, js).into_response()
 }
 
 pub(super) async fn get_object(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
@@ -60,69 +166,75 @@ pub(super) async fn get_object(request_headers: HeaderMap, Query(params): Query<
     let object_name = params.get("name");
     let object_type = params.get("type");
     if object_name.is_none() || object_type.is_none() {
-        return (StatusCode::BAD_REQUEST, "bad request").into_response();
+        return ApiError::bad_request("missing or invalid parameters").into_response();
     }
     let object_name = object_name.unwrap();
     let object_type = object_type.unwrap();
 
     let u = u.read().unwrap();
-    let etag = u.etag.clone();
-    if etag == client_etag {
-        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
-    }
-
-    let category_map = u.objects.get(object_type);
-    if category_map.is_none() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "category not found").into_response();
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
     }
-    let target = category_map.unwrap().iter().find(|x| x.object.filename == *object_name);
-    if target.is_none() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "object not found").into_response();
+    if u.graph.category(object_type).is_none() {
+        return ApiError::not_found("category not found").into_response();
     }
-    let js = serde_json::to_string(target.unwrap());
-    if js.is_err() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "internal error").into_response();
+    let Some(target) = u.find_object(object_type, object_name) else {
+        return ApiError::not_found("object not found").into_response();
+    };
+    // Objects have their own ETag, so that they stay cached while other parts of the registry change
+    let object_etag = &u.object_data[target].etag;
+    if *object_etag == client_etag {
+        return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
     }
+    let Ok(js) = serde_json::to_string(&WebRegistryObject::new(&u.graph, target)) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
     let mut headers = HeaderMap::new();
     headers.insert("Content-Type", HeaderValue::from_static("application/json"));
     headers.insert("Cache-Control", HeaderValue::from_static("max-age=1800, public, must-revalidate"));
-    headers.insert("ETag", HeaderValue::from_str(&etag).unwrap());
+    headers.insert("ETag", HeaderValue::from_str(object_etag).unwrap());
 
-    (headers, js.unwrap()).into_response()
+    (headers, js).into_response()
 }
 
-pub(super) async fn roa_handler_v4(State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", HeaderValue::from_static("text/plain"));
-    headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, stale-if-error=86400"));
+pub(super) async fn roa_handler_v4(request_headers: HeaderMap, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
     let u = u.read().unwrap();
-    if let Some(ref roa) = u.roa4  {
-        (headers, roa.clone()).into_response()
-    } else {
-        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    match u.roa4 {
+        Some(ref roa) => roa.response(&request_headers, "text/plain", ROA_CACHE_CONTROL),
+        None => roa_unavailable(&u).into_response(),
     }
 }
 
-pub(super) async fn roa_handler_v6(State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", HeaderValue::from_static("text/plain"));
-    headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, stale-if-error=86400"));
+pub(super) async fn roa_handler_v6(request_headers: HeaderMap, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
     let u = u.read().unwrap();
-    if let Some(ref roa) = u.roa6  {
-        (headers, roa.clone()).into_response()
-    } else {
-        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    match u.roa6 {
+        Some(ref roa) => roa.response(&request_headers, "text/plain", ROA_CACHE_CONTROL),
+        None => roa_unavailable(&u).into_response(),
     }
 }
 
-pub(super) async fn roa_handler_json(State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
-    let mut headers = HeaderMap::new();
-    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
-    headers.insert("Cache-Control", HeaderValue::from_static("max-age=3600, public, stale-if-error=86400"));
+pub(super) async fn roa_handler_json(request_headers: HeaderMap, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
     let u = u.read().unwrap();
-    if let Some(ref roa) = u.roa_json  {
-        (headers, roa.clone()).into_response()
+    if let Err(err) = ApiError::check_available(&u) {
+        return err.into_response();
+    }
+    match u.roa_json {
+        Some(ref roa) => roa.response(&request_headers, "application/json", ROA_CACHE_CONTROL),
+        None => roa_unavailable(&u).into_response(),
+    }
+}
+
+/// Error for missing ROA data, which is either disabled or failed to generate
+fn roa_unavailable(app_state: &AppState) -> ApiError {
+    if app_state.roa_disabled {
+        ApiError::not_found("ROA generation is disabled")
     } else {
-        (StatusCode::INTERNAL_SERVER_ERROR, "").into_response()
+        ApiError::Unavailable(String::from("The ROA data could not be generated"))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::IntoResponse;
+use serde::Serialize;
+use crate::modules::explorer::error::ApiError;
+use crate::modules::explorer::AppState;
+
+/// Separates the fields of the `git log` format
+const FIELD_SEPARATOR: char = '\x1f';
+
+#[derive(Serialize)]
+struct HistoryEntry {
+    commit: String,
+    author: String,
+    time: u64,
+    subject: String,
+}
+
+#[derive(Serialize)]
+struct ObjectRevision {
+    commit: String,
+    /// Object file content at the revision
+    content: String,
+    /// Unified diff from the revision to the currently loaded version
+    diff: String,
+}
+
+/// Commits that touched the file of an object, newest first. Renames are not followed, as [`at_handler`] only
+/// shows the object under its current file name.
+pub(super) async fn history_handler(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+    let (Some(object_type), Some(object_name)) = (params.get("type"), params.get("name")) else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+    let (etag, registry_root, file_path) = {
+        let u = u.read().unwrap();
+        if let Err(err) = ApiError::check_available(&u) {
+            return err.into_response();
+        }
+        if u.etag == client_etag {
+            return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+        }
+        let Some(file_path) = object_file_path(&u, object_type, object_name) else {
+            return ApiError::not_found("object not found").into_response();
+        };
+        (u.etag.clone(), u.registry_root.clone(), file_path)
+    };
+
+    let log = tokio::task::spawn_blocking(move || {
+        let format = format!("--format=%H{0}%an{0}%at{0}%s", FIELD_SEPARATOR);
+        run_git(&registry_root, &["log", format.as_str(), "--", file_path.as_str()])
+    }).await.ok().flatten();
+    let Some(log) = log else {
+        return ApiError::internal("history not available").into_response();
+    };
+    let history: Vec<HistoryEntry> = log.lines().filter_map(|line| {
+        let mut fields = line.splitn(4, FIELD_SEPARATOR);
+        Some(HistoryEntry {
+            commit: fields.next()?.to_string(),
+            author: fields.next()?.to_string(),
+            time: fields.next()?.parse().ok()?,
+            subject: fields.next()?.to_string(),
+        })
+    }).collect();
+
+    json_response(&etag, &history)
+}
+
+/// An object as of a revision, along with a diff against the currently loaded version
+pub(super) async fn at_handler(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+    let (Some(object_type), Some(object_name), Some(commit)) = (params.get("type"), params.get("name"), params.get("commit")) else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+    // Only accept (abbreviated) hashes, so that the revision can not be mistaken for an option of git
+    if !(4..=64).contains(&commit.len()) || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+        return ApiError::bad_request("invalid commit hash").into_response();
+    }
+    let (etag, registry_root, file_path, current_commit) = {
+        let u = u.read().unwrap();
+        if let Err(err) = ApiError::check_available(&u) {
+            return err.into_response();
+        }
+        if u.etag == client_etag {
+            return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+        }
+        let Some(file_path) = object_file_path(&u, object_type, object_name) else {
+            return ApiError::not_found("object not found").into_response();
+        };
+        (u.etag.clone(), u.registry_root.clone(), file_path, u.commit_hash.clone())
+    };
+
+    let commit = commit.clone();
+    let revision = tokio::task::spawn_blocking(move || {
+        // "./" makes the path relative to the registry root instead of the top of the repository
+        let content = run_git(&registry_root, &["show", format!("{}:./{}", commit, file_path).as_str()])?;
+        let current = if current_commit.chars().all(|c| c.is_ascii_hexdigit()) { current_commit.as_str() } else { "HEAD" };
+        let diff = run_git(&registry_root, &["diff", "--no-color", commit.as_str(), current, "--", file_path.as_str()])?;
+        Some(ObjectRevision { commit, content, diff })
+    }).await.ok().flatten();
+    let Some(revision) = revision else {
+        return ApiError::not_found("object not found at this revision").into_response();
+    };
+
+    json_response(&etag, &revision)
+}
+
+/// Path of the object file relative to the registry root
+fn object_file_path(app_state: &AppState, object_type: &str, object_name: &str) -> Option<String> {
+    app_state.find_object(object_type, object_name)
+        .map(|x| app_state.graph.node(x))
+        .map(|x| format!("data/{}/{}", x.data_dir, x.object.filename))
+}
+
+fn run_git(registry_root: &Path, args: &[&str]) -> Option<String> {
+    let cmd_output = Command::new("git")
+        .args(args)
+        .current_dir(registry_root)
+        .output().ok()?;
+    if !cmd_output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&cmd_output.stdout).into_owned())
+}
+
+fn json_response<T: Serialize>(etag: &str, value: &T) -> axum::response::Response {
+    let Ok(js) = serde_json::to_string(value) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=1800, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(etag).unwrap());
+    (headers, js).into_response()
+}
@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::IntoResponse;
+use serde::Serialize;
+use crate::modules::explorer::error::ApiError;
+use crate::modules::explorer::state::{AppState, Roa};
+use registry_core::registry_graph::NodeId;
+use crate::modules::registry_remove::{plan_removal, RemovalAction, RemovalCategory};
+
+const CONTACT_CATEGORIES: [&str; 2] = ["person", "role"];
+const ROUTE_CATEGORIES: [&str; 2] = ["route", "route6"];
+
+#[derive(Serialize)]
+struct MaintainerReport<'a> {
+    mntner: &'a str,
+    /// Object names by category of everything listing the mntner in `mnt-by`
+    objects: BTreeMap<&'a str, Vec<&'a str>>,
+    /// person and role objects referenced by the maintained objects
+    contacts: BTreeSet<String>,
+    /// ROAs generated for the maintained route objects (none if ROA generation is disabled)
+    roas: Option<BTreeMap<String, &'a Vec<Roa>>>,
+    problems: Vec<Problem>,
+    /// Changes `remove mnt` would make for this mntner (only if requested with `removal_check`)
+    removal: Option<RemovalReport>,
+}
+
+#[derive(Serialize)]
+struct Problem {
+    object: String,
+    kind: &'static str,
+    message: String,
+}
+
+#[derive(Serialize, Default)]
+struct RemovalReport {
+    removed: Vec<String>,
+    edited: Vec<String>,
+}
+
+/// Everything maintained by a mntner along with problems of these objects
+pub(super) async fn maintainer_handler(request_headers: HeaderMap, Query(params): Query<HashMap<String, String>>, State(u): State<Arc<RwLock<AppState>>>) -> impl IntoResponse {
+    let client_etag = request_headers.get("if-none-match")
+        .and_then(|v| v.to_str().ok()).unwrap_or_default();
+
+    let Some(name) = params.get("name") else {
+        return ApiError::bad_request("missing or invalid parameters").into_response();
+    };
+    let removal_check = params.get("removal_check").is_some_and(|x| x == "true" || x == "1");
+
+    let (graph, schema, mntner) = {
+        let u = u.read().unwrap();
+        if let Err(err) = ApiError::check_available(&u) {
+            return err.into_response();
+        }
+        if u.etag == client_etag {
+            return (StatusCode::NOT_MODIFIED, "Not Modified").into_response();
+        }
+        let Some(mntner_id) = u.find_object("mntner", name) else {
+            return ApiError::not_found("mntner not found").into_response();
+        };
+        (u.graph.clone(), u.schema.clone(), u.graph.node(mntner_id).object.filename.clone())
+    };
+    let mntner = &mntner;
+    // Planning the removal takes a while, it runs on the graph of the data the request started with
+    let removal = if removal_check {
+        let removal_list = vec![mntner.clone()];
+        let removal_graph = graph.clone();
+        let plan = tokio::task::spawn_blocking(move || {
+            plan_removal(&removal_graph, &schema, &removal_list, RemovalCategory::Mnt, false)
+                .map_err(|e| e.to_string())
+        }).await;
+        match plan {
+            Ok(Ok(plan)) => Some(plan),
+            Ok(Err(e)) => return ApiError::internal(format!("removal check failed: {}", e)).into_response(),
+            Err(_) => return ApiError::internal("removal check failed").into_response(),
+        }
+    } else {
+        None
+    };
+
+    let u = u.read().unwrap();
+    if !Arc::ptr_eq(&u.graph, &graph) {
+        return ApiError::unavailable("the registry data was reloaded during the removal check").into_response();
+    }
+    let Some(mntner_id) = u.graph.find("mntner", mntner) else {
+        return ApiError::not_found("mntner not found").into_response();
+    };
+    let registry = &u.graph;
+
+    // Problems are reported in the order of the object paths
+    let mut referencing: Vec<NodeId> = registry.node(mntner_id).get_back_links().iter().map(|x| x.1).collect();
+    referencing.sort_by_cached_key(|x| registry.path(*x));
+    let maintained: Vec<NodeId> = referencing.into_iter()
+        .chain([mntner_id])
+        .filter(|id| registry.node(*id).object.key_value.get("mnt-by").is_some_and(|x| x.iter().any(|(_, v)| v == mntner)))
+        .collect();
+
+    let roa_available = !u.roa_disabled && u.roa4.is_some();
+    let mut report = MaintainerReport {
+        mntner: &registry.node(mntner_id).object.filename,
+        objects: BTreeMap::new(),
+        contacts: BTreeSet::new(),
+        roas: roa_available.then(BTreeMap::new),
+        problems: Vec::new(),
+        removal: None,
+    };
+    for &id in &maintained {
+        let obj = registry.node(id);
+        let path = registry.path(id);
+        report.objects.entry(obj.schema_ref.as_str()).or_default().push(&obj.object.filename);
+        report.contacts.extend(obj.get_forward_links().iter()
+            .filter(|(_, target)| CONTACT_CATEGORIES.contains(&registry.node(*target).schema_ref.as_str()))
+            .map(|(_, target)| registry.path(*target)));
+
+        for (key, value) in &u.object_data[id].dangling_references {
+            report.problems.push(Problem {
+                object: path.clone(),
+                kind: "dangling_reference",
+                message: format!("{}: {} does not refer to an existing object", key, value),
+            });
+        }
+        if let Some(roas) = &mut report.roas
+            && ROUTE_CATEGORIES.contains(&obj.schema_ref.as_str()) {
+            match u.roas.get(&path) {
+                Some(route_roas) => {
+                    roas.insert(path, route_roas);
+                }
+                None => report.problems.push(Problem {
+                    object: path,
+                    kind: "filtered",
+                    message: String::from("No ROA generated, the route is invalid or filtered out by the filter set"),
+                }),
+            }
+        }
+    }
+    for list in report.objects.values_mut() {
+        list.sort_unstable();
+    }
+
+    if let Some(plan) = removal {
+        let mut removal_report = RemovalReport::default();
+        for action in plan.actions {
+            match action {
+                RemovalAction::RemoveObject(file) => {
+                    let is_maintained = maintained.iter().map(|x| registry.node(*x))
+                        .any(|obj| format!("data/{}/{}", obj.data_dir, obj.object.filename) == file);
+                    if !is_maintained {
+                        report.problems.push(Problem {
+                            object: file.trim_start_matches("data/").to_string(),
+                            kind: "removal",
+                            message: format!("Not maintained by {}, but would be removed along with it", mntner),
+                        });
+                    }
+                    removal_report.removed.push(file);
+                }
+                RemovalAction::DeleteLines(file, _) => removal_report.edited.push(file),
+            }
+        }
+        report.removal = Some(removal_report);
+    }
+
+    let Ok(js) = serde_json::to_string(&report) else {
+        return ApiError::internal("serialization failed").into_response();
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=1800, public, must-revalidate"));
+    headers.insert("ETag", HeaderValue::from_str(&u.etag).unwrap());
+    (headers, js).into_response()
+}
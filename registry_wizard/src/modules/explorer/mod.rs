@@ -1,42 +1,57 @@
 use crate::modules::util::BoxResult;
 use axum::routing::get;
-use axum::Router;
+use axum::{Extension, Router};
 use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::channel;
 use tower_http::compression::CompressionLayer;
-use crate::modules::explorer::state::AppState;
+#[cfg(feature = "daemon")]
+pub(in crate::modules) use crate::modules::explorer::state::{build_app_state, roa_entries, RoaFiles};
+pub(in crate::modules) use crate::modules::explorer::state::{AppState, DataStatus};
 use crate::modules::util::os_signals::{signal_listener, CustomSignal};
+use crate::modules::explorer::tls::TlsListener;
+pub use crate::modules::explorer::tls::TlsFiles;
 
 mod static_files;
+mod compressed;
+mod error;
+mod graph;
 mod handlers;
+mod history;
+mod maintainer;
+mod rdap;
 mod state;
+mod tls;
 
-pub fn start_explorer(registry_root: impl AsRef<Path>, port: u16, with_roa: bool) -> BoxResult<String> {
+/// Address, URL prefix and optional TLS settings of the explorer listener
+#[derive(Clone)]
+pub struct ListenOptions {
+    pub port: u16,
+    pub bind_ip: Option<String>,
+    pub base_path: String,
+    pub tls: Option<TlsFiles>,
+}
+
+pub fn start_explorer(registry_root: impl AsRef<Path>, listen: ListenOptions, with_roa: bool) -> BoxResult<String> {
     let registry_root: PathBuf = registry_root.as_ref().to_owned();
-    let app_state = Arc::new(RwLock::new(Default::default()));
+    let app_state: Arc<RwLock<AppState>> = Arc::new(RwLock::new(Default::default()));
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async move {
-        if let Err(err) = state::update_registry_data(registry_root.clone(), app_state.clone(), with_roa).await {
-            return Err(format!("Error reading registry data: {}", err));
-        }
-        if !with_roa {
-            app_state.write().unwrap().roa_disabled  = true;
-            const MSG_ROA_DISABLED: &str = "ROA data generation disabled";
-            app_state.write().unwrap().roa4 = Some(String::from(MSG_ROA_DISABLED));
-            app_state.write().unwrap().roa6 = Some(String::from(MSG_ROA_DISABLED));
-            app_state.write().unwrap().roa_json = Some(String::from(MSG_ROA_DISABLED));
-        }
+        app_state.write().unwrap().roa_disabled = !with_roa;
 
         let (sig_chan_tx, mut sig_chan_rx) = channel::<CustomSignal>(1);
         let signal_listener_handle = tokio::spawn(signal_listener(sig_chan_tx));
 
+        // The server is started before the initial load, answering API requests with 503 until it completes
+        let server = tokio::spawn(start_server(app_state.clone(), listen, sig_chan_rx.resubscribe()));
 
-        let sig_chan_rx_2 = sig_chan_rx.resubscribe();
-        let app_state_clone = app_state.clone();
         let registry_data_updater = tokio::spawn(async move {
+            if let Err(err) = state::update_registry_data(registry_root.clone(), app_state.clone(), with_roa).await {
+                return Err(format!("Error reading registry data: {}", err).into());
+            }
             loop {
                 match sig_chan_rx.recv().await.unwrap() {
                     CustomSignal::Shutdown => {
@@ -46,6 +61,7 @@ pub fn start_explorer(registry_root: impl AsRef<Path>, port: u16, with_roa: bool
                         eprintln!("Registry data update triggered");
                         if let Err(err) = state::update_registry_data(registry_root.clone(), app_state.clone(), with_roa).await {
                             eprintln!("Error updating registry data: {}", err);
+                            app_state.write().unwrap().status = DataStatus::ReloadFailed(err.to_string());
                         }
                         eprintln!("Registry data update completed")
                     }
@@ -54,7 +70,6 @@ pub fn start_explorer(registry_root: impl AsRef<Path>, port: u16, with_roa: bool
             Ok(())
         });
 
-        let server = tokio::spawn(start_server(app_state_clone, port, sig_chan_rx_2));
         let result = tokio::try_join!(
             async {registry_data_updater.await?},
             async {server.await?},
@@ -70,33 +85,82 @@ pub fn start_explorer(registry_root: impl AsRef<Path>, port: u16, with_roa: bool
 }
 
 
-async fn start_server(app_state: Arc<RwLock<AppState>>, port: u16, mut sig_chan_rx: broadcast::Receiver<CustomSignal>) -> BoxResult<()> {
-    let addr = SocketAddr::from((IpAddr::from(Ipv6Addr::UNSPECIFIED), port));
+pub(in crate::modules) async fn start_server(app_state: Arc<RwLock<AppState>>, listen: ListenOptions, mut sig_chan_rx: broadcast::Receiver<CustomSignal>) -> BoxResult<()> {
+    let bind_ip = if let Some(bind_ip) = &listen.bind_ip {
+        IpAddr::from_str(bind_ip.as_str())?
+    } else {
+        IpAddr::from(Ipv6Addr::UNSPECIFIED)
+    };
+    let addr = SocketAddr::from((bind_ip, listen.port));
+    let base_path = normalize_base_path(&listen.base_path);
+
     let listener = tokio::net::TcpListener::bind(&addr).await
         .map_err(|x| format!("Error listening on TCP: {}", x))?;
-    eprintln!("Starting server on port {}. Send the POSIX 'SIGUSR1' signal to this process to trigger data update", port);
+    let scheme = if listen.tls.is_some() { "https" } else { "http" };
+    eprintln!("Starting server on {} ({}://{}{}/). Send the POSIX 'SIGUSR1' signal to this process to trigger data update",
+              addr, scheme, addr, base_path);
     if !app_state.read().unwrap().roa_disabled {
-        eprintln!("ROA data endpoints: '/api/roa/v4/', '/api/roa/v6/', '/api/roa/json/'");
+        eprintln!("ROA data endpoints: '{0}/api/roa/v4/', '{0}/api/roa/v6/', '{0}/api/roa/json/'", base_path);
     }
+    eprintln!("RDAP endpoints: '{0}/rdap/ip/', '{0}/rdap/autnum/', '{0}/rdap/domain/', '{0}/rdap/entity/'", base_path);
 
     let app = Router::new()
         .route("/", get(handlers::root_handler).layer(CompressionLayer::new()))
         .route("/{*path}", get(handlers::root_handler).layer(CompressionLayer::new()))
         .route("/api/index/", get(handlers::index_handler).layer(CompressionLayer::new()))
+        .route("/api/index/{category}", get(handlers::category_index_handler).layer(CompressionLayer::new()))
+        .route("/api/info/", get(handlers::info_handler).layer(CompressionLayer::new()))
+        .route("/api/search/", get(handlers::search_handler).layer(CompressionLayer::new()))
         .route("/api/object/", get(handlers::get_object).layer(CompressionLayer::new()))
+        .route("/api/object/history", get(history::history_handler).layer(CompressionLayer::new()))
+        .route("/api/object/at", get(history::at_handler).layer(CompressionLayer::new()))
+        .route("/api/mntner", get(maintainer::maintainer_handler).layer(CompressionLayer::new()))
+        .route("/api/graph/related", get(graph::related_handler).layer(CompressionLayer::new()))
+        .route("/api/graph/path", get(graph::path_handler).layer(CompressionLayer::new()))
         .route("/api/roa/v4/", get(handlers::roa_handler_v4))
         .route("/api/roa/v6/", get(handlers::roa_handler_v6))
         .route("/api/roa/json/", get(handlers::roa_handler_json))
-        .with_state(app_state);
+        .route("/rdap/ip/{*address}", get(rdap::rdap_ip).layer(CompressionLayer::new()))
+        .route("/rdap/autnum/{asn}", get(rdap::rdap_autnum).layer(CompressionLayer::new()))
+        .route("/rdap/domain/{name}", get(rdap::rdap_domain).layer(CompressionLayer::new()))
+        .route("/rdap/entity/{handle}", get(rdap::rdap_entity).layer(CompressionLayer::new()))
+        .with_state(app_state)
+        .layer(Extension(Scheme(scheme)));
+    let app = if base_path.is_empty() {
+        app
+    } else {
+        Router::new()
+            .route(&format!("{}/", base_path), get(handlers::base_path_handler).layer(CompressionLayer::new()))
+            .nest(&base_path, app)
+    };
 
-    axum::serve(listener, app).with_graceful_shutdown(async move {
+    let shutdown = async move {
         loop {
             match sig_chan_rx.recv().await.unwrap() {
                 CustomSignal::Shutdown => { break }
                 CustomSignal::DataUpdate => {}
             }
         }
-    }).await
-        .map_err(|e| format!("Error starting server: {}", e))?;
+    };
+    if let Some(tls_files) = &listen.tls {
+        let listener = TlsListener::new(listener, tls_files)?;
+        axum::serve(listener, app).with_graceful_shutdown(shutdown).await
+    } else {
+        axum::serve(listener, app).with_graceful_shutdown(shutdown).await
+    }.map_err(|e| format!("Error starting server: {}", e))?;
     Ok(())
 }
+
+/// URL scheme the explorer is served with, for building absolute links
+#[derive(Clone, Copy)]
+pub(super) struct Scheme(pub &'static str);
+
+/// URL prefix the explorer is served under, with a leading and without a trailing slash ("" for the root)
+fn normalize_base_path(base_path: &str) -> String {
+    let base_path = base_path.trim_matches('/');
+    if base_path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", base_path)
+    }
+}
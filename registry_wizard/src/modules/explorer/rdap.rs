@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use axum::extract::{OriginalUri, Path, State};
+use axum::Extension;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use cidr_utils::cidr::IpCidr;
+use serde_json::{json, Map, Value};
+use crate::modules::explorer::graph::line_key;
+use crate::modules::explorer::state::AppState;
+use crate::modules::explorer::Scheme;
+use crate::modules::object_finder::{find_most_specific, normalize_asn};
+use registry_core::object_reader::{OrderedObjectLine, RegistryObject};
+use registry_core::registry_graph::NodeId;
+use crate::modules::zone_builder::{parse_nserver, DsRecord};
+
+const ENTITY_CATEGORIES: [&str; 4] = ["person", "role", "mntner", "organisation"];
+
+pub(super) async fn rdap_ip(headers: HeaderMap, OriginalUri(uri): OriginalUri, Extension(scheme): Extension<Scheme>, Path(address): Path<String>, State(u): State<Arc<RwLock<AppState>>>) -> Response {
+    let search_cidr = if let Ok(ip) = IpAddr::from_str(&address) {
+        IpCidr::new_host(ip)
+    } else if let Ok(cidr) = IpCidr::from_str(&address) {
+        cidr
+    } else {
+        return rdap_error(StatusCode::BAD_REQUEST, "Invalid IP address or prefix");
+    };
+    let category = if search_cidr.is_ipv4() { "inetnum" } else { "inet6num" };
+
+    let u = u.read().unwrap();
+    if let Some(reason) = u.status.unavailable_reason() {
+        return rdap_error(StatusCode::SERVICE_UNAVAILABLE, &reason);
+    }
+    let candidates: Vec<(NodeId, &str)> = u.graph.category(category).unwrap_or_default().iter()
+        .map(|id| (*id, u.graph.node(*id).object.filename.as_str()))
+        .collect();
+    let Some(&(id, name)) = find_most_specific(&candidates, |x| x.1, &search_cidr) else {
+        return rdap_error(StatusCode::NOT_FOUND, "No network found for the address");
+    };
+    let object = &u.graph.node(id).object;
+
+    let object_cidr = IpCidr::from_str(&name.replace('_', "/")).unwrap_or(search_cidr);
+    let mut result = rdap_object(&u, &base_url(&headers, &uri, scheme), id, "ip network", &format!("ip/{}", object_cidr), true);
+    result.insert("startAddress".into(), json!(object_cidr.first_address().to_string()));
+    result.insert("endAddress".into(), json!(object_cidr.last_address().to_string()));
+    result.insert("ipVersion".into(), json!(if object_cidr.is_ipv4() { "v4" } else { "v6" }));
+    if let Some(name) = first_value(object, "netname") {
+        result.insert("name".into(), json!(name));
+    }
+    if let Some(policy) = first_value(object, "policy") {
+        result.insert("type".into(), json!(policy));
+    }
+    if let Some(country) = first_value(object, "country") {
+        result.insert("country".into(), json!(country));
+    }
+    // The next less specific network is the parent
+    let parents: Vec<&str> = candidates.iter()
+        .map(|x| x.1)
+        .filter(|x| *x != name)
+        .collect();
+    if let Some(parent) = find_most_specific(&parents, |x| x, &object_cidr) {
+        result.insert("parentHandle".into(), json!(parent));
+    }
+    rdap_response(result, &u.commit_hash)
+}
+
+pub(super) async fn rdap_autnum(headers: HeaderMap, OriginalUri(uri): OriginalUri, Extension(scheme): Extension<Scheme>, Path(asn): Path<String>, State(u): State<Arc<RwLock<AppState>>>) -> Response {
+    let Ok(asn) = normalize_asn(&asn) else {
+        return rdap_error(StatusCode::BAD_REQUEST, "Invalid AS number");
+    };
+    let u = u.read().unwrap();
+    if let Some(reason) = u.status.unavailable_reason() {
+        return rdap_error(StatusCode::SERVICE_UNAVAILABLE, &reason);
+    }
+    let Some(id) = find_object(&u, &["aut-num"], &asn) else {
+        return rdap_error(StatusCode::NOT_FOUND, "AS number not found");
+    };
+    let object = &u.graph.node(id).object;
+
+    let number: u32 = asn.trim_start_matches("AS").parse().unwrap_or_default();
+    let mut result = rdap_object(&u, &base_url(&headers, &uri, scheme), id, "autnum", &format!("autnum/{}", number), true);
+    result.insert("startAutnum".into(), json!(number));
+    result.insert("endAutnum".into(), json!(number));
+    if let Some(name) = first_value(object, "as-name") {
+        result.insert("name".into(), json!(name));
+    }
+    rdap_response(result, &u.commit_hash)
+}
+
+pub(super) async fn rdap_domain(headers: HeaderMap, OriginalUri(uri): OriginalUri, Extension(scheme): Extension<Scheme>, Path(name): Path<String>, State(u): State<Arc<RwLock<AppState>>>) -> Response {
+    let name = name.trim_end_matches('.').to_lowercase();
+    let u = u.read().unwrap();
+    if let Some(reason) = u.status.unavailable_reason() {
+        return rdap_error(StatusCode::SERVICE_UNAVAILABLE, &reason);
+    }
+    let Some(id) = find_object(&u, &["dns"], &name) else {
+        return rdap_error(StatusCode::NOT_FOUND, "Domain not found");
+    };
+    let object = &u.graph.node(id).object;
+
+    let base_url = base_url(&headers, &uri, scheme);
+    let mut result = rdap_object(&u, &base_url, id, "domain", &format!("domain/{}", name), true);
+    result.insert("ldhName".into(), json!(name));
+
+    // Group the nserver lines by host, collecting the glue addresses
+    let mut nameservers: BTreeMap<String, (Vec<String>, Vec<String>)> = BTreeMap::new();
+    for line in values(object, "nserver") {
+        let (host, address) = parse_nserver(&line);
+        if host.is_empty() {
+            continue;
+        }
+        let entry = nameservers.entry(host.trim_end_matches('.').to_lowercase()).or_default();
+        match address {
+            Some(IpAddr::V4(v4)) => entry.0.push(v4.to_string()),
+            Some(IpAddr::V6(v6)) => entry.1.push(v6.to_string()),
+            None => {}
+        }
+    }
+    let nameservers: Vec<Value> = nameservers.into_iter().map(|(host, (v4, v6))| {
+        let mut nameserver = json!({ "objectClassName": "nameserver", "ldhName": host });
+        if !v4.is_empty() || !v6.is_empty() {
+            nameserver["ipAddresses"] = json!({ "v4": v4, "v6": v6 });
+        }
+        nameserver
+    }).collect();
+    if !nameservers.is_empty() {
+        result.insert("nameservers".into(), json!(nameservers));
+    }
+
+    let ds_data: Vec<Value> = values(object, "ds-rdata").iter()
+        .filter_map(|x| DsRecord::from_str(x).ok())
+        .map(|ds| json!({
+            "keyTag": ds.key_tag,
+            "algorithm": ds.algorithm,
+            "digestType": ds.digest_type,
+            "digest": ds.digest,
+        }))
+        .collect();
+    result.insert("secureDNS".into(), json!({
+        "delegationSigned": !ds_data.is_empty(),
+        "dsData": ds_data,
+    }));
+    rdap_response(result, &u.commit_hash)
+}
+
+pub(super) async fn rdap_entity(headers: HeaderMap, OriginalUri(uri): OriginalUri, Extension(scheme): Extension<Scheme>, Path(handle): Path<String>, State(u): State<Arc<RwLock<AppState>>>) -> Response {
+    let u = u.read().unwrap();
+    if let Some(reason) = u.status.unavailable_reason() {
+        return rdap_error(StatusCode::SERVICE_UNAVAILABLE, &reason);
+    }
+    let Some(id) = find_object(&u, &ENTITY_CATEGORIES, &handle) else {
+        return rdap_error(StatusCode::NOT_FOUND, "Entity not found");
+    };
+    let result = entity(&u, &base_url(&headers, &uri, scheme), id, &[], true);
+    rdap_response(result, &u.commit_hash)
+}
+
+/// Fields common to all object classes: handle, self link, remarks and the linked contacts as entities
+fn rdap_object(state: &AppState, base_url: &str, id: NodeId, class_name: &str,
+               self_path: &str, with_entities: bool) -> Map<String, Value> {
+    let object = &state.graph.node(id).object;
+    let mut result = Map::new();
+    result.insert("objectClassName".into(), json!(class_name));
+    result.insert("handle".into(), json!(object.filename));
+    let href = format!("{}{}", base_url, self_path);
+    result.insert("links".into(), json!([{ "value": href, "rel": "self", "href": href, "type": "application/rdap+json" }]));
+
+    let remarks: Vec<Value> = ["descr", "remarks"].iter()
+        .flat_map(|key| values(object, key))
+        .map(|x| json!({ "description": x.split('\n').collect::<Vec<_>>() }))
+        .collect();
+    if !remarks.is_empty() {
+        result.insert("remarks".into(), json!(remarks));
+    }
+
+    if with_entities {
+        let entities: Vec<Value> = linked_entities(state, id).into_iter()
+            .map(|(target, roles)| Value::Object(entity(state, base_url, target, &roles, false)))
+            .collect();
+        if !entities.is_empty() {
+            result.insert("entities".into(), json!(entities));
+        }
+    }
+    result
+}
+
+fn entity(state: &AppState, base_url: &str, id: NodeId, roles: &[&str], with_entities: bool) -> Map<String, Value> {
+    let node = state.graph.node(id);
+    let object = &node.object;
+    let mut result = rdap_object(state, base_url, id, "entity", &format!("entity/{}", object.filename), with_entities);
+    let (full_name, kind) = match node.schema_ref.as_str() {
+        "person" => (first_value(object, "person"), "individual"),
+        "role" => (first_value(object, "role"), "group"),
+        "organisation" => (first_value(object, "org-name"), "org"),
+        _ => (None, "group"),
+    };
+    let mut vcard = vec![
+        json!(["version", {}, "text", "4.0"]),
+        json!(["fn", {}, "text", full_name.unwrap_or(object.filename.clone())]),
+        json!(["kind", {}, "text", kind]),
+    ];
+    for email in values(object, "e-mail") {
+        vcard.push(json!(["email", {}, "text", email]));
+    }
+    result.insert("vcardArray".into(), json!(["vcard", vcard]));
+    if !roles.is_empty() {
+        result.insert("roles".into(), json!(roles));
+    }
+    result
+}
+
+/// The contact objects linked from an object along with their RDAP roles
+fn linked_entities(state: &AppState, id: NodeId) -> Vec<(NodeId, Vec<&'static str>)> {
+    let mut entities: Vec<(NodeId, Vec<&str>)> = Vec::new();
+    let object = state.graph.node(id);
+    for (line_number, target) in object.get_forward_links() {
+        if !ENTITY_CATEGORIES.contains(&state.graph.node(*target).schema_ref.as_str()) || *target == id {
+            continue;
+        }
+        let role = match line_key(&object.object, *line_number).unwrap_or_default() {
+            "admin-c" => "administrative",
+            "tech-c" | "zone-c" => "technical",
+            "abuse-c" => "abuse",
+            "mnt-by" => "registrant",
+            _ => continue,
+        };
+        match entities.iter_mut().find(|(x, _)| x == target) {
+            Some((_, roles)) if !roles.contains(&role) => roles.push(role),
+            Some(_) => {}
+            None => entities.push((*target, vec![role])),
+        }
+    }
+    entities
+}
+
+fn find_object(state: &AppState, categories: &[&str], name: &str) -> Option<NodeId> {
+    categories.iter().find_map(|c| state.find_object(c, name))
+}
+
+fn values(object: &RegistryObject<OrderedObjectLine>, key: &str) -> Vec<String> {
+    object.key_value.get(key)
+        .map(|lines| lines.iter().map(|(_, value)| value.clone()).collect())
+        .unwrap_or_default()
+}
+
+fn first_value(object: &RegistryObject<OrderedObjectLine>, key: &str) -> Option<String> {
+    values(object, key).into_iter().next()
+}
+
+fn base_url(headers: &HeaderMap, uri: &Uri, scheme: Scheme) -> String {
+    let host = headers.get("host").and_then(|v| v.to_str().ok())
+        .or(uri.authority().map(|x| x.as_str()))
+        .unwrap_or("localhost");
+    let scheme = headers.get("x-forwarded-proto").and_then(|v| v.to_str().ok()).unwrap_or(scheme.0);
+    // Keep the base path the explorer is served under
+    let prefix = uri.path().split_once("/rdap/").map(|x| x.0).unwrap_or_default();
+    format!("{}://{}{}/rdap/", scheme, host, prefix)
+}
+
+fn rdap_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("Content-Type", HeaderValue::from_static("application/rdap+json"));
+    headers.insert("Access-Control-Allow-Origin", HeaderValue::from_static("*"));
+    headers
+}
+
+fn rdap_response(mut result: Map<String, Value>, commit_hash: &str) -> Response {
+    result.insert("rdapConformance".into(), json!(["rdap_level_0"]));
+    result.insert("notices".into(), json!([{
+        "title": "Source",
+        "description": [format!("dn42 registry data (commit {})", commit_hash)],
+    }]));
+    let mut headers = rdap_headers();
+    headers.insert("Cache-Control", HeaderValue::from_static("max-age=1800, public, must-revalidate"));
+    (headers, Value::Object(result).to_string()).into_response()
+}
+
+fn rdap_error(status: StatusCode, description: &str) -> Response {
+    let body = json!({
+        "rdapConformance": ["rdap_level_0"],
+        "errorCode": status.as_u16(),
+        "title": status.canonical_reason().unwrap_or_default(),
+        "description": [description],
+    });
+    (status, rdap_headers(), body.to_string()).into_response()
+}
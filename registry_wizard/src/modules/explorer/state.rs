@@ -1,63 +1,149 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
+use axum::body::Bytes;
+use cidr_utils::cidr::IpCidr;
 use serde::Serialize;
-use crate::modules::object_reader::{OrderedObjectLine, RegistryObject};
-use crate::modules::registry_graph::{create_registry_graph, parse_registry_schema, LinkInfoLineNumberOnly, RegistryGraph};
-use crate::modules::util::{get_current_unix_time, get_git_commit_hash, BoxResult};
+use sha2::{Digest, Sha256};
+use crate::modules::explorer::compressed::{content_hash, etag, PrecompressedBody};
+use crate::modules::object_finder::normalize_asn;
+use registry_core::object_reader::{OrderedObjectLine, RegistryObject};
+use registry_core::registry_graph::{create_registry_graph, LinkInfoLineNumberOnly, NodeId, ServerGraph};
+use registry_core::schema::{parse_registry_schema, Schema};
+use crate::modules::util::{get_current_unix_time, get_git_commit_hash, print_warnings, BoxResult};
 
 
 #[derive(Default)]
-pub(super) struct AppState {
-    pub objects: HashMap<String, Vec<WebRegistryObject>>,
-    pub index: HashMap<String, Vec<String>>,
+pub(in crate::modules) struct AppState {
+    pub graph: Arc<ServerGraph>,
+    /// The schema the graph was built with
+    pub schema: Arc<Vec<Schema>>,
+    /// Data of each graph node that is served along with it, indexed by `NodeId`
+    pub object_data: Vec<ObjectData>,
+    /// Object names of each category, sorted by their uppercase form for prefix searches
+    pub index: HashMap<String, Vec<IndexEntry>>,
+    /// Response of `/api/index/` with the full index, serialized once per reload
+    pub index_json: Bytes,
+    /// Response of `/api/info/`
+    pub info_json: Bytes,
+    /// Hash of the registry commit and content, only changes if the data does
     pub etag: String,
-    pub generation_time: String,
     pub commit_hash: String,
-    pub roa4: Option<String>,
-    pub roa6: Option<String>,
-    pub roa_json: Option<String>,
-    pub roa_disabled: bool
+    pub registry_root: PathBuf,
+    pub roa4: Option<PrecompressedBody>,
+    pub roa6: Option<PrecompressedBody>,
+    pub roa_json: Option<PrecompressedBody>,
+    /// Generated ROAs by route object path (i.e. `route/172.20.0.0_24`)
+    pub roas: HashMap<String, Vec<Roa>>,
+    pub roa_disabled: bool,
+    pub status: DataStatus,
 }
 
+impl AppState {
+    /// Find an object by its name as written by users or in references: case-insensitively,
+    /// with or without the `AS` prefix for aut-num objects and with prefixes in slash notation
+    pub fn find_object(&self, category: &str, name: &str) -> Option<NodeId> {
+        let key = lookup_key(category, name);
+        let entries = self.index.get(category)?;
+        Some(entries[entries.binary_search_by(|x| x.key.as_str().cmp(&key)).ok()?].node)
+    }
+}
+
+/// Uppercase name of an object (the key of its index entry), with the name aliases of the category resolved
+fn lookup_key(category: &str, name: &str) -> String {
+    let name = name.trim();
+    let name = match category {
+        "aut-num" => normalize_asn(name).ok(),
+        "inetnum" | "inet6num" | "route" | "route6" => IpCidr::from_str(&name.replace('_', "/")).ok()
+            .map(|cidr| format!("{}_{}", cidr.first_address(), cidr.network_length())),
+        _ => None,
+    }.unwrap_or_else(|| name.to_string());
+    name.to_uppercase()
+}
+
+/// Whether the registry data can be served
+#[derive(Default, Clone)]
+pub(in crate::modules) enum DataStatus {
+    /// The initial load has not completed yet
+    #[default]
+    Loading,
+    Ready,
+    /// The last reload failed, the data is still the one of the previous load
+    ReloadFailed(String),
+}
+
+impl DataStatus {
+    /// Why the data can not be served, if it can not
+    pub fn unavailable_reason(&self) -> Option<String> {
+        match self {
+            DataStatus::Loading => Some(String::from("The registry data is being loaded")),
+            DataStatus::Ready => None,
+            DataStatus::ReloadFailed(err) => Some(format!("Reloading the registry data failed: {}", err)),
+        }
+    }
+}
+
+/// An object as served by `/api/object/`, with its links as `category/name` paths
 #[derive(Debug, Serialize)]
-pub(super) struct WebRegistryObject {
-    pub object: RegistryObject<OrderedObjectLine>,
-    pub category: String,
+pub(in crate::modules) struct WebRegistryObject<'a> {
+    pub object: &'a RegistryObject<OrderedObjectLine>,
+    pub category: &'a str,
     pub back_links: Vec<String>,
     pub forward_links: Vec<(LinkInfoLineNumberOnly, String)>,
 }
 
+impl<'a> WebRegistryObject<'a> {
+    pub fn new(graph: &'a ServerGraph, id: NodeId) -> Self {
+        let node = graph.node(id);
+        // Sorted, as the order depends on the order the graph was built in
+        let mut back_links: Vec<String> = node.get_back_links().iter()
+            .map(|x| graph.path(x.1)).collect();
+        back_links.sort_unstable();
+        WebRegistryObject {
+            object: &node.object,
+            category: &node.schema_ref,
+            back_links,
+            forward_links: node.get_forward_links().iter().map(|(li, x)| (*li, graph.path(*x))).collect(),
+        }
+    }
+}
+
+pub(in crate::modules) struct ObjectData {
+    /// Lookup keys (and their values) not referencing any existing object
+    pub dangling_references: Vec<(String, String)>,
+    /// Content hash of the serialized object
+    pub etag: String,
+}
+
+pub(in crate::modules) struct IndexEntry {
+    /// Uppercase name used for case-insensitive matching
+    pub key: String,
+    pub name: String,
+    pub node: NodeId,
+}
+
+/// The ROA files served by the explorer
+pub(in crate::modules) struct RoaFiles {
+    pub v4: String,
+    pub v6: String,
+    pub json: String,
+    pub roas: HashMap<String, Vec<Roa>>,
+}
+
+#[derive(Serialize)]
+pub(in crate::modules) struct Roa {
+    pub prefix: String,
+    pub max_length: u8,
+    pub asn: String,
+}
 
 pub(super) async fn update_registry_data(registry_root: PathBuf, app_state: Arc<RwLock<AppState>>, with_roa: bool) -> BoxResult<()> {
-    let schema = parse_registry_schema(registry_root.as_ref(), false)?;
-    let graph: RegistryGraph<(), OrderedObjectLine, LinkInfoLineNumberOnly> = create_registry_graph(registry_root.as_ref(), &schema, true, true)?;
+    let schema = Arc::new(print_warnings(parse_registry_schema(registry_root.as_ref(), false)?));
+    let graph: ServerGraph = print_warnings(create_registry_graph(registry_root.as_ref(), &schema, true, true)?);
     let commit_hash = get_git_commit_hash(&registry_root).unwrap_or(String::from("N/A"));
-    
-    let mut graph_web = HashMap::with_capacity(graph.capacity());
-    let mut index_map: HashMap<String, Vec<String>> = HashMap::with_capacity(graph.capacity());
-    for (c, x) in &graph {
-        let mut list = Vec::with_capacity(x.len());
-        let mut name_list = Vec::with_capacity(x.len());
-        for elem in x {
-            let v = WebRegistryObject {
-                object: elem.object.clone(),
-                category: elem.schema_ref.clone(),
-                back_links: elem.get_back_links()
-                    .map(|x| x.1.schema_ref.clone() + "/" + &x.1.object.filename).collect(),
-                forward_links: elem.get_forward_links()
-                    .map(|(li, obj)| (li, obj.schema_ref.clone() + "/" + &obj.object.filename)).collect(),
-            };
-            name_list.push(elem.object.filename.clone());
-            list.push(v);
-        }
-        index_map.insert(c.clone(), name_list);
-        graph_web.insert(c.clone(), list);
-    }
 
-    let mut roa4 = None;
-    let mut roa6 = None;
-    let mut roa_json = None;
+    let mut roa_files = None;
     if with_roa {
         let data = roa_wizard::get_roa_data_v4v6(&registry_root, |warn| {
             eprintln!("Warning during ROA data generation: {}", warn);
@@ -66,21 +152,151 @@ pub(super) async fn update_registry_data(registry_root: PathBuf, app_state: Arc<
             eprintln!("Warning during ROA data generation: {}", err);
         }).ok();
         if let Some((mut v4, v6)) = data {
-            roa4 = Some(v4.output_bird(&registry_root));
-            roa6 = Some(v6.output_bird(&registry_root));
+            let roa4 = v4.output_bird(&registry_root);
+            let roa6 = v6.output_bird(&registry_root);
             v4.merge(v6);
-            roa_json = Some(v4.output_json());
+            let roas = roa_entries(&v4);
+            roa_files = Some(RoaFiles { v4: roa4, v6: roa6, json: v4.output_json(), roas });
         }
     }
 
-    let mut app_state_lock = app_state.write().unwrap();
-    app_state_lock.objects = graph_web;
-    app_state_lock.index = index_map;
-    app_state_lock.etag = format!("\"{}\"", get_current_unix_time());
-    app_state_lock.generation_time = get_current_unix_time().to_string();
-    app_state_lock.commit_hash = commit_hash;
-    app_state_lock.roa4 = roa4;
-    app_state_lock.roa6 = roa6;
-    app_state_lock.roa_json = roa_json;
+    let roa_disabled = app_state.read().unwrap().roa_disabled;
+    let new_state = build_app_state(Arc::new(graph), schema, &registry_root, commit_hash, roa_files, roa_disabled);
+    *app_state.write().unwrap() = new_state;
     Ok(())
+}
+
+pub(in crate::modules) fn build_app_state(graph: Arc<ServerGraph>, schema: Arc<Vec<Schema>>, registry_root: &Path, commit_hash: String, roa_files: Option<RoaFiles>, roa_disabled: bool) -> AppState {
+    let object_data: Vec<ObjectData> = graph.nodes().map(|(id, elem)| {
+        let mut dangling_references = Vec::new();
+        let lookup_keys = schema.iter().find(|x| x.schema_ref == elem.schema_ref).into_iter()
+            .flat_map(|x| x.keys.iter())
+            .filter(|x| !x.lookup_targets.is_empty());
+        for lookup_key in lookup_keys {
+            for (line_number, value) in elem.object.key_value.get(&lookup_key.key).into_iter().flatten() {
+                if !elem.get_forward_links().iter().any(|(li, _)| li == line_number) {
+                    dangling_references.push((lookup_key.key.clone(), value.clone()));
+                }
+            }
+        }
+        let web_object = WebRegistryObject::new(&graph, id);
+        let etag = etag(&content_hash(&serde_json::to_vec(&web_object).unwrap_or_default()));
+        ObjectData { dangling_references, etag }
+    }).collect();
+
+    let mut index_map: HashMap<String, Vec<IndexEntry>> = HashMap::with_capacity(graph.categories().len());
+    for (c, x) in graph.categories() {
+        let mut name_list: Vec<IndexEntry> = x.iter()
+            .map(|id| {
+                let name = &graph.node(*id).object.filename;
+                IndexEntry { key: name.to_uppercase(), name: name.clone(), node: *id }
+            })
+            .collect();
+        name_list.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        index_map.insert(c.clone(), name_list);
+    }
+
+    // Without ROA files, the ROA endpoints respond with 404 if disabled and 503 if the generation failed
+    let (roa4, roa6, roa_json, roas) = if roa_disabled {
+        (None, None, None, HashMap::new())
+    } else if let Some(roa_files) = roa_files {
+        let roa_etag = |kind: &str| roa_etag(kind, &roa_files.roas, &commit_hash);
+        (Some(PrecompressedBody::new(roa_files.v4, roa_etag("v4"))), Some(PrecompressedBody::new(roa_files.v6, roa_etag("v6"))),
+         Some(PrecompressedBody::new(roa_files.json, roa_etag("json"))), roa_files.roas)
+    } else {
+        (None, None, None, HashMap::new())
+    };
+
+    let etag = registry_etag(&graph, &object_data, &roas, &commit_hash, roa_disabled);
+    let generation_time = get_current_unix_time().to_string();
+    let (index_json, info_json) = serialize_index(&index_map, &commit_hash, &generation_time, roa_disabled);
+
+    AppState {
+        graph,
+        schema,
+        object_data,
+        index: index_map,
+        index_json,
+        info_json,
+        etag,
+        commit_hash,
+        registry_root: registry_root.to_path_buf(),
+        roa4,
+        roa6,
+        roa_json,
+        roas,
+        roa_disabled,
+        status: DataStatus::Ready,
+    }
+}
+
+/// ETag of the data shared by most API responses, derived from the commit and the content of all objects
+/// and ROAs so that reloading an unchanged registry keeps client caches valid
+fn registry_etag(graph: &ServerGraph, object_data: &[ObjectData], roas: &HashMap<String, Vec<Roa>>, commit_hash: &str, roa_disabled: bool) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(commit_hash);
+    hasher.update([roa_disabled as u8]);
+    let mut object_etags: Vec<(&str, &str, &str)> = graph.nodes()
+        .map(|(id, x)| (x.schema_ref.as_str(), x.object.filename.as_str(), object_data[id].etag.as_str()))
+        .collect();
+    object_etags.sort_unstable();
+    for (category, name, object_etag) in object_etags {
+        hasher.update(format!("{}/{} {}\n", category, name, object_etag));
+    }
+    hash_roas(&mut hasher, roas);
+    etag(&hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// ETag of a ROA file (`v4`, `v6` or `json`), derived from the commit and the ROAs as the file contains its
+/// generation time
+fn roa_etag(kind: &str, roas: &HashMap<String, Vec<Roa>>, commit_hash: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{} {}\n", kind, commit_hash));
+    hash_roas(&mut hasher, roas);
+    etag(&hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+fn hash_roas(hasher: &mut Sha256, roas: &HashMap<String, Vec<Roa>>) {
+    let mut routes: Vec<_> = roas.iter().collect();
+    routes.sort_unstable_by_key(|x| x.0);
+    for (route, entries) in routes {
+        for roa in entries {
+            hasher.update(format!("{} {} {} {}\n", route, roa.prefix, roa.max_length, roa.asn));
+        }
+    }
+}
+
+fn serialize_index(index: &HashMap<String, Vec<IndexEntry>>, commit_hash: &str, generation_time: &str, roa_disabled: bool) -> (Bytes, Bytes) {
+    let mut info = serde_json::Map::new();
+    info.insert("commit".to_string(), serde_json::Value::from(commit_hash));
+    info.insert("roa".to_string(), serde_json::Value::from(!roa_disabled));
+    info.insert("time".to_string(), serde_json::Value::from(generation_time));
+
+    let names: HashMap<&str, Vec<&str>> = index.iter()
+        .map(|(c, x)| (c.as_str(), x.iter().map(|x| x.name.as_str()).collect()))
+        .collect();
+    let index_json = serde_json::json!({"i": info, "d": names});
+
+    let counts: HashMap<&str, usize> = index.iter().map(|(c, x)| (c.as_str(), x.len())).collect();
+    info.insert("counts".to_string(), serde_json::json!(counts));
+    (Bytes::from(index_json.to_string()), Bytes::from(serde_json::Value::Object(info).to_string()))
+}
+
+/// The ROAs generated for each route object, keyed by the path of the object
+pub(in crate::modules) fn roa_entries(data: &roa_wizard::RoaData) -> HashMap<String, Vec<Roa>> {
+    let mut roas: HashMap<String, Vec<Roa>> = HashMap::new();
+    for route in data.object_list() {
+        let category = if route.prefix.is_ipv4() { "route" } else { "route6" };
+        let address = route.prefix.first_address();
+        let length = route.prefix.network_length();
+        let entries = roas.entry(format!("{}/{}_{}", category, address, length)).or_default();
+        for origin in &route.origins {
+            entries.push(Roa {
+                prefix: format!("{}/{}", address, length),
+                max_length: route.max_length.unwrap_or(length),
+                asn: format!("AS{}", origin),
+            });
+        }
+    }
+    roas
 }
\ No newline at end of file
@@ -10,27 +10,56 @@ const statDisplayInner = document.getElementById("statDisplayInner");
 const errorDisplayDiv = document.getElementById("errorDisplayDiv");
 const moreInfoLink = document.getElementById("moreInfoLink");
 const infoDialog = document.getElementById("infoDialog");
+const graphDetails = document.getElementById("graphDetails");
+const graphForm = document.getElementById("graphForm");
+const graphStatus = document.getElementById("graphStatus");
+const graphSvg = document.getElementById("graphSvg");
+const pathForm = document.getElementById("pathForm");
+const pathStatus = document.getElementById("pathStatus");
+const pathDisplay = document.getElementById("pathDisplay");
+const historyDetails = document.getElementById("historyDetails");
+const historyStatus = document.getElementById("historyStatus");
+const historyDisplay = document.getElementById("historyDisplay");
+const revisionDisplay = document.getElementById("revisionDisplay");
+const mntnerDetails = document.getElementById("mntnerDetails");
+const mntnerStatus = document.getElementById("mntnerStatus");
+const mntnerDisplay = document.getElementById("mntnerDisplay");
+const removalCheckButton = document.getElementById("removalCheckButton");
 
-let index = null;
 let info = null;
 
-async function fetch_index() {
-    if (index !== null) {
+async function fetch_info() {
+    if (info !== null) {
         return;
     }
     try {
-        const response = await fetch("api/index/");
-        const json = await response.json();
-        info = json["i"];
-        index = json["d"];
+        const response = await fetch("api/info/");
+        if (!response.ok) {
+            errorDisplayDiv.innerText = await error_detail(response);
+            set_page_state("error");
+            return;
+        }
+        info = await response.json();
     } catch (e) {
         console.log(e);
-        errorDisplayDiv.innerText = "Error fetching index";
+        errorDisplayDiv.innerText = "Error fetching registry information";
         set_page_state("error");
     }
 }
 
+// Detail of an API error response
+async function error_detail(response) {
+    try {
+        return (await response.json())["detail"];
+    } catch (e) {
+        return response.statusText;
+    }
+}
+
+let search_counter = 0;
+
 async function perform_search(query) {
+    const search_id = ++search_counter;
     if (query.length < 2) {
         expectedWindowHash = "";
         window.location.hash = "";
@@ -39,70 +68,98 @@ async function perform_search(query) {
     }
 
     set_page_state("wait");
-    await fetch_index();
-
-    searchDisplayDiv.innerHTML = "";
-    set_page_state("search");
     const orig_query = query;
 
-    let search_category = "";
+    const params = new URLSearchParams();
     if (query.includes("/")) {
         const [a, b] = query.split("/");
-        search_category = a;
+        params.set("category", a);
         query = b;
     }
-    query = query.toUpperCase();
+    params.set("query", query);
 
-    let result_count = 0;
-    let target_result_count = 100;
+    let offset = 0;
+    let total = 0;
     let last_result = null;
-    let last_category = null;
-    const gen = filter_results(search_category, query);
+    let aborted = false;
 
-    function next_target_results() {
-        while (target_result_count === -1 || result_count < target_result_count) {
-            const {value, done} = gen.next();
-            if (done) {
-                break;
+    // Returns whether results were added
+    async function fetch_results(limit) {
+        params.set("offset", offset.toString());
+        params.set("limit", limit.toString());
+        let page = null;
+        try {
+            const response = await fetch("api/search/?" + params.toString());
+            if (!response.ok) {
+                errorDisplayDiv.innerText = await error_detail(response);
+                set_page_state("error");
+                aborted = true;
+                return false;
             }
-            const [result_category, result] = value;
-            result_count++;
-            last_category = result_category;
-            last_result = result;
+            page = await response.json();
+        } catch (e) {
+            console.log(e);
+            errorDisplayDiv.innerText = "Error fetching search results";
+            set_page_state("error");
+            aborted = true;
+            return false;
+        }
+        if (search_id !== search_counter) {
+            // A newer search was started in the meantime
+            aborted = true;
+            return false;
+        }
+        total = page["total"];
+        for (const [result_category, result] of page["items"]) {
+            last_result = [result_category, result];
             append_search_result(result_category, result);
         }
-        if (result_count === target_result_count) {
-            // Hit result limit
-            const btnDiv = document.createElement("div");
-            btnDiv.classList.add("showMoreBtnDiv");
-            const moreBtn = document.createElement("button");
-            moreBtn.innerText = "Show more..";
-            moreBtn.onclick = () => {
-                target_result_count += 100;
-                moreBtn.disabled = true;
-                btnDiv.parentNode.removeChild(btnDiv);
-                next_target_results();
-            };
-            btnDiv.appendChild(moreBtn);
-            const allBtn = document.createElement("button");
-            allBtn.innerText = "Show all..";
-            allBtn.onclick = () => {
-                target_result_count = -1;
-                allBtn.disabled = true;
-                btnDiv.parentNode.removeChild(btnDiv);
-                next_target_results();
-            };
-            btnDiv.appendChild(allBtn);
-            searchDisplayDiv.appendChild(btnDiv);
+        offset += page["items"].length;
+        return page["items"].length > 0;
+    }
+
+    function add_more_buttons() {
+        if (offset >= total) {
+            return;
         }
+        // Hit result limit
+        const btnDiv = document.createElement("div");
+        btnDiv.classList.add("showMoreBtnDiv");
+        const moreBtn = document.createElement("button");
+        moreBtn.innerText = "Show more..";
+        moreBtn.onclick = async () => {
+            moreBtn.disabled = true;
+            btnDiv.parentNode.removeChild(btnDiv);
+            if (await fetch_results(100)) {
+                add_more_buttons();
+            }
+        };
+        btnDiv.appendChild(moreBtn);
+        const allBtn = document.createElement("button");
+        allBtn.innerText = "Show all..";
+        allBtn.onclick = async () => {
+            allBtn.disabled = true;
+            btnDiv.parentNode.removeChild(btnDiv);
+            while (offset < total && await fetch_results(1000)) {
+                // Fetch all remaining pages
+            }
+        };
+        btnDiv.appendChild(allBtn);
+        searchDisplayDiv.appendChild(btnDiv);
     }
 
-    next_target_results();
+    searchDisplayDiv.innerHTML = "";
+    await fetch_results(100);
+    if (aborted) {
+        return;
+    }
+    set_page_state("search");
+    add_more_buttons();
 
-    if (result_count === 1) {
-        await display_object(last_category, last_result, true);
+    if (total === 1) {
+        await display_object(last_result[0], last_result[1], true);
         return;
-    } else if (result_count === 0) {
+    } else if (total === 0) {
         searchDisplayDiv.innerText = "No results";
     }
     expectedWindowHash = `?${orig_query}`;
@@ -127,18 +184,6 @@ function append_search_result(object_type, object_name) {
     searchDisplayDiv.appendChild(div);
 }
 
-function* filter_results(search_category, query) {
-    for (const category of Object.entries(index)) {
-        if (search_category !== "" && category[0] !== search_category) {
-            continue;
-        }
-        const filtered = category[1].filter((d) => d.toUpperCase().includes(query));
-        for (const result of filtered) {
-            yield [category[0], result];
-        }
-    }
-}
-
 let last_displayed_object = "";
 
 async function display_object(object_type, object_name, no_set_search) {
@@ -162,15 +207,29 @@ async function display_object(object_type, object_name, no_set_search) {
     params.set("type", object_type);
     let response = null;
     try {
-        response = await (await fetch("api/object/?" + params.toString())).json();
+        const object_response = await fetch("api/object/?" + params.toString());
+        if (!object_response.ok) {
+            last_displayed_object = null;
+            errorDisplayDiv.innerText = await error_detail(object_response);
+            set_page_state("error");
+            return;
+        }
+        response = await object_response.json();
     } catch (e) {
         console.log(e);
+        last_displayed_object = null;
         set_page_state("error");
         errorDisplayDiv.innerText = "Error fetching object";
         return;
     }
 
     const name = get_object_path(response["category"], response["object"]["filename"]);
+    if (name !== provided_obj_path) {
+        // The object was requested by an alias, e.g. in a different case or a prefix in slash notation
+        last_displayed_object = name;
+        expectedWindowHash = `/${name}`;
+        history.replaceState(null, "", `#/${name}`);
+    }
 
     if (no_set_search !== true) {
         searchBox.value = name;
@@ -298,6 +357,20 @@ async function display_object(object_type, object_name, no_set_search) {
         backLinkDisplay.innerText = "No references found";
     }
     set_page_state("object");
+    pathDisplay.innerHTML = "";
+    pathStatus.innerText = "";
+    reset_graph();
+    reset_history();
+    reset_mntner();
+    if (graphDetails.open) {
+        await draw_graph();
+    }
+    if (historyDetails.open) {
+        await load_history();
+    }
+    if (mntnerDetails.open && object_type === "mntner") {
+        await load_mntner(false);
+    }
 }
 
 function* getBackLinks(back_links) {
@@ -306,9 +379,535 @@ function* getBackLinks(back_links) {
     }
 }
 
+const SVG_NS = "http://www.w3.org/2000/svg";
+let graph_simulation = null;
+
+function reset_graph() {
+    if (graph_simulation !== null) {
+        cancelAnimationFrame(graph_simulation);
+        graph_simulation = null;
+    }
+    graphSvg.innerHTML = "";
+    graphSvg.classList.add("noDisplay");
+    graphStatus.innerText = "";
+}
+
+async function draw_graph() {
+    const object_path = last_displayed_object;
+    const params = object_params();
+    for (const [key, value] of new FormData(graphForm)) {
+        params.set(key, key === "no_cross_dn42_mnt" ? "true" : value);
+    }
+    reset_graph();
+    graphStatus.innerText = "Loading...";
+    let response = null;
+    try {
+        const graph_response = await fetch("api/graph/related?" + params.toString());
+        if (!graph_response.ok) {
+            graphStatus.innerText = await error_detail(graph_response);
+            return;
+        }
+        response = await graph_response.json();
+    } catch (e) {
+        console.log(e);
+        graphStatus.innerText = "Error fetching related objects";
+        return;
+    }
+    if (object_path !== last_displayed_object) {
+        // Another object was opened in the meantime
+        return;
+    }
+    graphStatus.innerText = `${response["nodes"].length} objects, ${response["edges"].length} links`;
+    graphSvg.classList.remove("noDisplay");
+    render_graph(response["nodes"], response["edges"]);
+}
+
+function category_color(category) {
+    let hash = 0;
+    for (const c of category) {
+        hash = (hash * 31 + c.charCodeAt(0)) % 360;
+    }
+    return `hsl(${hash}, 60%, 55%)`;
+}
+
+function render_graph(nodes, edges) {
+    const width = graphSvg.clientWidth;
+    const height = graphSvg.clientHeight;
+    const view = {x: -width / 2, y: -height / 2, scale: 1};
+    const update_view = () => {
+        graphSvg.setAttribute("viewBox", `${view.x} ${view.y} ${width / view.scale} ${height / view.scale}`);
+    };
+    update_view();
+
+    const edge_layer = document.createElementNS(SVG_NS, "g");
+    const node_layer = document.createElementNS(SVG_NS, "g");
+    graphSvg.appendChild(edge_layer);
+    graphSvg.appendChild(node_layer);
+
+    const node_map = new Map();
+    nodes.forEach((node, i) => {
+        // Start on a spiral so that the simulation does not begin with overlapping nodes
+        const angle = i * 2.4;
+        const radius = 10 * Math.sqrt(i);
+        node.x = node.root ? 0 : Math.cos(angle) * radius;
+        node.y = node.root ? 0 : Math.sin(angle) * radius;
+        node.vx = 0;
+        node.vy = 0;
+        node.fixed = false;
+
+        const group = document.createElementNS(SVG_NS, "g");
+        const circle = document.createElementNS(SVG_NS, "circle");
+        circle.setAttribute("r", node.root ? "9" : "6");
+        circle.setAttribute("fill", category_color(node.category));
+        if (node.highlight) {
+            circle.classList.add("highlight");
+        } else if (node.root) {
+            circle.classList.add("root");
+        }
+        const title = document.createElementNS(SVG_NS, "title");
+        title.textContent = node.id;
+        circle.appendChild(title);
+        const label = document.createElementNS(SVG_NS, "text");
+        label.setAttribute("x", "10");
+        label.setAttribute("y", "4");
+        label.textContent = node.name;
+        group.appendChild(circle);
+        group.appendChild(label);
+        node_layer.appendChild(group);
+        node.elem = group;
+        node_map.set(node.id, node);
+
+        let drag_start = null;
+        circle.onpointerdown = (ev) => {
+            ev.stopPropagation();
+            circle.setPointerCapture(ev.pointerId);
+            drag_start = {x: ev.clientX, y: ev.clientY, moved: false};
+            node.fixed = true;
+        };
+        circle.onpointermove = (ev) => {
+            if (drag_start === null) {
+                return;
+            }
+            const dx = (ev.clientX - drag_start.x) / view.scale;
+            const dy = (ev.clientY - drag_start.y) / view.scale;
+            if (Math.abs(dx) + Math.abs(dy) > 2) {
+                drag_start.moved = true;
+            }
+            if (drag_start.moved) {
+                node.x += dx;
+                node.y += dy;
+                drag_start.x = ev.clientX;
+                drag_start.y = ev.clientY;
+                restart_simulation();
+            }
+        };
+        circle.onpointerup = () => {
+            const moved = drag_start !== null && drag_start.moved;
+            drag_start = null;
+            if (!moved) {
+                node.fixed = false;
+                const [a, b] = node.id.split("/");
+                display_object(a, b).then();
+            }
+        };
+    });
+
+    const links = [];
+    for (const edge of edges) {
+        const source = node_map.get(edge.source);
+        const target = node_map.get(edge.target);
+        if (source === undefined || target === undefined) {
+            continue;
+        }
+        const line = document.createElementNS(SVG_NS, "line");
+        const title = document.createElementNS(SVG_NS, "title");
+        title.textContent = `${edge.source} ${edge.key} ${edge.target}`;
+        line.appendChild(title);
+        edge_layer.appendChild(line);
+        links.push({source, target, elem: line});
+    }
+
+    let energy = 1;
+    function step() {
+        // Repulsion between all nodes, springs along the links and a weak pull to the center
+        for (let i = 0; i < nodes.length; i++) {
+            const a = nodes[i];
+            for (let j = i + 1; j < nodes.length; j++) {
+                const b = nodes[j];
+                const dx = b.x - a.x || 0.01;
+                const dy = b.y - a.y || 0.01;
+                const dist_sq = Math.max(dx * dx + dy * dy, 25);
+                const force = 800 / dist_sq;
+                const dist = Math.sqrt(dist_sq);
+                a.vx -= force * dx / dist;
+                a.vy -= force * dy / dist;
+                b.vx += force * dx / dist;
+                b.vy += force * dy / dist;
+            }
+        }
+        for (const link of links) {
+            const dx = link.target.x - link.source.x;
+            const dy = link.target.y - link.source.y;
+            const dist = Math.sqrt(dx * dx + dy * dy) || 0.01;
+            const force = (dist - 60) * 0.03;
+            link.source.vx += force * dx / dist;
+            link.source.vy += force * dy / dist;
+            link.target.vx -= force * dx / dist;
+            link.target.vy -= force * dy / dist;
+        }
+        let total = 0;
+        for (const node of nodes) {
+            node.vx = (node.vx - node.x * 0.005) * 0.6;
+            node.vy = (node.vy - node.y * 0.005) * 0.6;
+            if (!node.fixed) {
+                node.x += node.vx * energy;
+                node.y += node.vy * energy;
+            }
+            total += Math.abs(node.vx) + Math.abs(node.vy);
+        }
+        energy *= 0.99;
+        return total / Math.max(nodes.length, 1);
+    }
+
+    function draw() {
+        for (const node of nodes) {
+            node.elem.setAttribute("transform", `translate(${node.x},${node.y})`);
+        }
+        for (const link of links) {
+            link.elem.setAttribute("x1", link.source.x);
+            link.elem.setAttribute("y1", link.source.y);
+            link.elem.setAttribute("x2", link.target.x);
+            link.elem.setAttribute("y2", link.target.y);
+        }
+    }
+
+    function tick() {
+        const movement = step();
+        draw();
+        if (movement * energy > 0.05) {
+            graph_simulation = requestAnimationFrame(tick);
+        } else {
+            graph_simulation = null;
+        }
+    }
+
+    function restart_simulation() {
+        energy = Math.max(energy, 0.3);
+        if (graph_simulation === null) {
+            graph_simulation = requestAnimationFrame(tick);
+        } else {
+            draw();
+        }
+    }
+
+    let pan_start = null;
+    graphSvg.onpointerdown = (ev) => {
+        graphSvg.setPointerCapture(ev.pointerId);
+        pan_start = {x: ev.clientX, y: ev.clientY};
+    };
+    graphSvg.onpointermove = (ev) => {
+        if (pan_start === null) {
+            return;
+        }
+        view.x -= (ev.clientX - pan_start.x) / view.scale;
+        view.y -= (ev.clientY - pan_start.y) / view.scale;
+        pan_start = {x: ev.clientX, y: ev.clientY};
+        update_view();
+    };
+    graphSvg.onpointerup = () => {
+        pan_start = null;
+    };
+    graphSvg.onwheel = (ev) => {
+        ev.preventDefault();
+        const rect = graphSvg.getBoundingClientRect();
+        // Keep the point under the cursor in place while zooming
+        const px = view.x + (ev.clientX - rect.left) / view.scale;
+        const py = view.y + (ev.clientY - rect.top) / view.scale;
+        view.scale = Math.min(Math.max(view.scale * (ev.deltaY < 0 ? 1.15 : 1 / 1.15), 0.1), 8);
+        view.x = px - (ev.clientX - rect.left) / view.scale;
+        view.y = py - (ev.clientY - rect.top) / view.scale;
+        update_view();
+    };
+
+    draw();
+    graph_simulation = requestAnimationFrame(tick);
+}
+
+async function find_path(dst) {
+    const params = new URLSearchParams();
+    params.set("src", last_displayed_object);
+    params.set("dst", dst);
+    pathDisplay.innerHTML = "";
+    pathStatus.innerText = "Loading...";
+    let response = null;
+    try {
+        response = await fetch("api/graph/path?" + params.toString());
+    } catch (e) {
+        console.log(e);
+        pathStatus.innerText = "Error fetching path";
+        return;
+    }
+    if (!response.ok) {
+        pathStatus.innerText = await error_detail(response);
+        return;
+    }
+    const hops = await response.json();
+    pathStatus.innerText = `${hops.length - 1} hops`;
+    for (const hop of hops) {
+        if (hop["key"] !== null) {
+            const tr = document.createElement("tr");
+            const td = document.createElement("td");
+            td.classList.add("pathKey");
+            // The arrow points from the referencing object to the referenced one
+            td.innerText = hop["reverse"] ? `\u2191 ${hop["key"]}` : `\u2193 ${hop["key"]}`;
+            tr.appendChild(td);
+            pathDisplay.appendChild(tr);
+        }
+        const tr = document.createElement("tr");
+        const td = document.createElement("td");
+        td.appendChild(create_object_link(hop["category"], hop["name"]));
+        tr.appendChild(td);
+        pathDisplay.appendChild(tr);
+    }
+}
+
+pathForm.onsubmit = async (ev) => {
+    ev.preventDefault();
+    await find_path(new FormData(pathForm).get("dst").trim());
+};
+function create_object_link(object_type, object_name) {
+    const fragment = document.createDocumentFragment();
+    const link_elem = document.createElement("a");
+    link_elem.href = `#/${get_object_path(object_type, object_name)}`;
+    link_elem.onclick = (ev) => {
+        ev.preventDefault();
+        display_object(object_type, object_name);
+    };
+    link_elem.innerText = object_name;
+    fragment.appendChild(link_elem);
+    const badge = document.createElement("span");
+    badge.classList.add("badge");
+    badge.innerText = object_type;
+    fragment.appendChild(badge);
+    return fragment;
+}
+
+let mntner_loaded = false;
+
+function reset_mntner() {
+    mntner_loaded = false;
+    mntnerDisplay.innerHTML = "";
+    mntnerStatus.innerText = "";
+    removalCheckButton.disabled = false;
+    if (last_displayed_object.startsWith("mntner/")) {
+        mntnerDetails.classList.remove("noDisplay");
+    } else {
+        mntnerDetails.classList.add("noDisplay");
+    }
+}
+
+async function load_mntner(removal_check) {
+    const object_path = last_displayed_object;
+    const params = new URLSearchParams();
+    params.set("name", object_path.split("/")[1]);
+    if (removal_check) {
+        params.set("removal_check", "true");
+    }
+    mntner_loaded = true;
+    mntnerStatus.innerText = removal_check ? "Checking removal impact..." : "Loading...";
+    let report = null;
+    try {
+        const response = await fetch("api/mntner?" + params.toString());
+        if (!response.ok) {
+            mntnerStatus.innerText = await error_detail(response);
+            return;
+        }
+        report = await response.json();
+    } catch (e) {
+        console.log(e);
+        mntnerStatus.innerText = "Error fetching maintainer data";
+        return;
+    }
+    if (object_path !== last_displayed_object) {
+        return;
+    }
+    mntnerStatus.innerText = "";
+    mntnerDisplay.innerHTML = "";
+
+    function add_section(title, rows) {
+        const heading = document.createElement("h4");
+        heading.innerText = title;
+        mntnerDisplay.appendChild(heading);
+        const table = document.createElement("table");
+        for (const cells of rows) {
+            const tr = document.createElement("tr");
+            for (const cell of cells) {
+                const td = document.createElement("td");
+                td.append(cell);
+                tr.appendChild(td);
+            }
+            table.appendChild(tr);
+        }
+        if (rows.length === 0) {
+            table.innerText = "None";
+        }
+        mntnerDisplay.appendChild(table);
+    }
+
+    add_section("Problems", report["problems"].map((problem) => {
+        const [a, b] = problem["object"].split("/");
+        return [create_object_link(a, b), problem["kind"], problem["message"]];
+    }));
+    for (const [category, names] of Object.entries(report["objects"])) {
+        add_section(`${category} (${names.length})`, names.map((name) => [create_object_link(category, name)]));
+    }
+    add_section("Contacts", report["contacts"].map((contact) => {
+        const [a, b] = contact.split("/");
+        return [create_object_link(a, b)];
+    }));
+    if (report["roas"] !== null) {
+        add_section("ROAs", Object.values(report["roas"]).flat().map((roa) =>
+            [roa["prefix"], `max ${roa["max_length"]}`, roa["asn"]]
+        ));
+    }
+    if (report["removal"] !== null) {
+        add_section("Removed by 'remove mnt'", report["removal"]["removed"].map((x) => [x]));
+        add_section("Edited by 'remove mnt'", report["removal"]["edited"].map((x) => [x]));
+        removalCheckButton.disabled = true;
+    }
+}
+
+mntnerDetails.ontoggle = async () => {
+    if (mntnerDetails.open && !mntner_loaded) {
+        await load_mntner(false);
+    }
+};
+removalCheckButton.onclick = async () => {
+    removalCheckButton.disabled = true;
+    await load_mntner(true);
+};
+
+let history_loaded = false;
+
+function reset_history() {
+    history_loaded = false;
+    historyDisplay.innerHTML = "";
+    historyStatus.innerText = "";
+    revisionDisplay.classList.add("noDisplay");
+}
+
+function object_params() {
+    const [object_type, object_name] = last_displayed_object.split("/");
+    const params = new URLSearchParams();
+    params.set("type", object_type);
+    params.set("name", object_name);
+    return params;
+}
+
+async function load_history() {
+    const object_path = last_displayed_object;
+    reset_history();
+    history_loaded = true;
+    historyStatus.innerText = "Loading...";
+    let history = null;
+    try {
+        const response = await fetch("api/object/history?" + object_params().toString());
+        if (!response.ok) {
+            historyStatus.innerText = await error_detail(response);
+            return;
+        }
+        history = await response.json();
+    } catch (e) {
+        console.log(e);
+        historyStatus.innerText = "Error fetching history";
+        return;
+    }
+    if (object_path !== last_displayed_object) {
+        return;
+    }
+    historyStatus.innerText = `${history.length} commits`;
+    for (const entry of history) {
+        const tr = document.createElement("tr");
+        const td_time = document.createElement("td");
+        td_time.innerText = new Date(entry["time"] * 1000).toLocaleString();
+        const td_author = document.createElement("td");
+        td_author.innerText = entry["author"];
+        const td_subject = document.createElement("td");
+        const link_elem = document.createElement("a");
+        link_elem.href = "#";
+        link_elem.title = entry["commit"];
+        link_elem.innerText = entry["subject"];
+        link_elem.onclick = async (ev) => {
+            ev.preventDefault();
+            await load_revision(entry["commit"]);
+        };
+        td_subject.appendChild(link_elem);
+        tr.appendChild(td_time);
+        tr.appendChild(td_author);
+        tr.appendChild(td_subject);
+        historyDisplay.appendChild(tr);
+    }
+}
+
+async function load_revision(commit) {
+    const params = object_params();
+    params.set("commit", commit);
+    revisionDisplay.classList.add("noDisplay");
+    let revision = null;
+    try {
+        const response = await fetch("api/object/at?" + params.toString());
+        if (!response.ok) {
+            historyStatus.innerText = await error_detail(response);
+            return;
+        }
+        revision = await response.json();
+    } catch (e) {
+        console.log(e);
+        historyStatus.innerText = "Error fetching revision";
+        return;
+    }
+    document.getElementById("revisionTitle").innerText = revision["commit"];
+    document.getElementById("revisionContent").innerText = revision["content"];
+    const diff_elem = document.getElementById("revisionDiff");
+    diff_elem.innerHTML = "";
+    if (revision["diff"] === "") {
+        diff_elem.innerText = "No changes";
+    }
+    for (const line of revision["diff"].split("\n")) {
+        const span = document.createElement("span");
+        if (line.startsWith("+") && !line.startsWith("+++")) {
+            span.classList.add("diffAdd");
+        } else if (line.startsWith("-") && !line.startsWith("---")) {
+            span.classList.add("diffRemove");
+        }
+        span.innerText = line + "\n";
+        diff_elem.appendChild(span);
+    }
+    revisionDisplay.classList.remove("noDisplay");
+}
+
+historyDetails.ontoggle = async () => {
+    if (historyDetails.open && !history_loaded) {
+        await load_history();
+    }
+};
+
+graphDetails.ontoggle = async () => {
+    if (graphDetails.open && graphSvg.classList.contains("noDisplay")) {
+        await draw_graph();
+    }
+};
+graphForm.onsubmit = async (ev) => {
+    ev.preventDefault();
+    await draw_graph();
+};
+
 async function get_stats() {
-    await fetch_index();
-    const entries = Object.entries(index).sort((a, b) => a[0].localeCompare(b[0]));
+    await fetch_info();
+    if (info === null) {
+        return;
+    }
+    const entries = Object.entries(info["counts"]).sort((a, b) => a[0].localeCompare(b[0]));
     for (const category of entries) {
         const elem = document.createElement("div");
         const href = document.createElement("a");
@@ -320,7 +919,7 @@ async function get_stats() {
             searchBox.value = category[0] + "/";
         };
         const span = document.createElement("span");
-        span.innerText = " - " + category[1].length;
+        span.innerText = " - " + category[1];
         elem.appendChild(href);
         elem.appendChild(span);
         statDisplayInner.appendChild(elem);
@@ -407,28 +1006,14 @@ async function handle_window_hash() {
 }
 
 async function navigate_to_window_hash(target) {
-    set_page_state("wait");
-    await fetch_index();
-    const [a, b] = target.split("/");
-    let found = false;
-    for (const category of Object.entries(index)) {
-        if (category[0] === a) {
-            for (const item of category[1]) {
-                if (item === b) {
-                    found = true;
-                    break;
-                }
-            }
-            break;
-        }
-    }
-
-    if (!found) {
+    // The name may contain slashes (prefixes in slash notation), the server resolves such aliases
+    const separator = target.indexOf("/");
+    if (separator === -1) {
         errorDisplayDiv.innerText = "Object linked to was not found";
         set_page_state("error");
         return;
     }
-    await display_object(a, b);
+    await display_object(target.substring(0, separator), target.substring(separator + 1));
 }
 
 let searchTimeout = null;
@@ -460,7 +1045,7 @@ moreInfoLink.onclick = async () => {
         infoDialog.close();
     };
     inner_wait.classList.remove("noDisplay");
-    await fetch_index();
+    await fetch_info();
     inner_wait.classList.add("noDisplay");
     const with_roa = info["roa"];
     inner.querySelector('[data-commit]').textContent = info["commit"];
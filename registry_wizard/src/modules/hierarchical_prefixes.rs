@@ -4,7 +4,7 @@ use std::rc::Rc;
 use std::str::FromStr;
 use cidr_utils::cidr::IpCidr;
 use serde::{Serialize};
-use crate::modules::object_reader::{read_registry_objects, RegistryObject, SimpleObjectLine};
+use registry_core::object_reader::{read_registry_objects, RegistryObject, SimpleObjectLine};
 use crate::modules::util::BoxResult;
 
 type PrefixTree = Rc<RefCell<HierarchicalPrefix>>;
@@ -1,5 +1,5 @@
 use std::path::{Path, PathBuf};
-use crate::modules::object_reader::{registry_objects_to_iter, RegistryObjectIterator, SimpleObjectLine};
+use registry_core::object_reader::{registry_objects_to_iter, RegistryObjectIterator, SimpleObjectLine};
 use crate::modules::util::{get_item_list, get_last_git_activity, BoxResult, EitherOr};
 
 pub fn output(registry_root: &Path, data_input: EitherOr<String, String>, cutoff_time: Option<u64>) -> BoxResult<String> {
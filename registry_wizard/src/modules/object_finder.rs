@@ -1,44 +1,262 @@
-use crate::modules::object_reader::{
-    read_registry_object_kv, registry_objects_to_iter, RegistryObjectIterator, SimpleObjectLine,
+use registry_core::object_reader::{
+    read_registry_object_kv, read_registry_objects, registry_objects_to_iter, ObjectLine, RegistryObject,
+    RegistryObjectIterator, SimpleObjectLine,
 };
-use crate::modules::util::BoxResult;
+use registry_core::schema::parse_registry_schema;
+use crate::modules::util::{print_warnings, BoxResult};
 use cidr_utils::cidr::IpCidr;
+use regex::Regex;
+use serde::Serialize;
 use std::net::IpAddr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-pub fn output(registry_root: &Path, search_ip: &str) -> BoxResult<String> {
+pub enum FinderQuery {
+    Ip(String),
+    Asn(String),
+    Mnt(String),
+    Person(String),
+    Prefix(String),
+    Attr { key: String, value_pattern: String },
+}
+
+#[derive(Debug, Serialize)]
+pub(in crate::modules) struct FoundObject {
+    pub category: String,
+    pub object: RegistryObject<SimpleObjectLine>,
+}
+
+pub fn output(registry_root: &Path, query: FinderQuery, whois_format: bool) -> BoxResult<String> {
+    let found = match query {
+        FinderQuery::Ip(search_ip) => {
+            let found = find_smallest_inetnum(registry_root, &search_ip)?;
+            if !whois_format {
+                let found = found.ok_or("Failed to find target")?;
+                return Ok(serde_json::to_string(&found.object.key_value)? + "\n");
+            }
+            // In whois format a missing object is reported like for the other queries
+            found.into_iter().collect()
+        }
+        FinderQuery::Asn(asn) => find_by_asn(registry_root, &asn)?,
+        FinderQuery::Mnt(mnt) => find_by_mnt(registry_root, &mnt)?,
+        FinderQuery::Person(nic_hdl) => find_by_person(registry_root, &nic_hdl)?,
+        FinderQuery::Prefix(prefix) => find_by_prefix(registry_root, &prefix)?,
+        FinderQuery::Attr { key, value_pattern } => find_by_attr(registry_root, &key, &value_pattern)?,
+    };
+
+    if whois_format {
+        let objects: Vec<_> = found.iter().map(|x| (x.category.as_str(), &x.object)).collect();
+        Ok(format_whois(&objects))
+    } else {
+        Ok(serde_json::to_string(&found)? + "\n")
+    }
+}
+
+pub(in crate::modules) fn find_smallest_inetnum(registry_root: &Path, search_ip: &str) -> BoxResult<Option<FoundObject>> {
     let search_ip = IpAddr::from_str(search_ip)?;
 
-    let sub_path = if search_ip.is_ipv4() {
-        Path::new("data/inetnum/")
+    let category = if search_ip.is_ipv4() {
+        "inetnum"
     } else {
-        Path::new("data/inet6num/")
+        "inet6num"
     };
+    let sub_path = PathBuf::from("data/").join(category);
 
     let mut registry_objects: RegistryObjectIterator<SimpleObjectLine> =
-        registry_objects_to_iter(registry_root, sub_path)?;
+        registry_objects_to_iter(registry_root, &sub_path)?;
     registry_objects.set_enumerate_only(true);
-    let mut length = 0;
-    let mut current: Option<String> = None;
+    let mut filenames = Vec::new();
     for obj in registry_objects {
-        let filename = obj?.filename;
-        let obj_cidr = IpCidr::from_str(filename.replace('_', "/").as_str());
-        if let Ok(obj_cidr) = obj_cidr {
-            if obj_cidr.contains(&search_ip) && obj_cidr.network_length() > length {
-                length = obj_cidr.network_length();
-                current = Some(filename);
-            }
-        } else {
-            eprint!("Failed to parse object '{}'", filename);
+        filenames.push(obj?.filename);
+    }
+
+    let Some(current) = find_most_specific(&filenames, |x| x.as_str(), &IpCidr::new_host(search_ip)) else {
+        return Ok(None);
+    };
+    let target_path = registry_root.join(sub_path).join(current);
+    let key_value = read_registry_object_kv::<SimpleObjectLine>(target_path.as_path())?;
+    Ok(Some(FoundObject {
+        category: category.to_string(),
+        object: RegistryObject { key_value, filename: current.clone() },
+    }))
+}
+
+/// Get the most specific of the prefix objects (named like '172.20.0.0_16') covering the searched prefix
+pub(in crate::modules) fn find_most_specific<'a, T, F>(items: &'a [T], get_filename: F, search_cidr: &IpCidr) -> Option<&'a T>
+where
+    F: Fn(&T) -> &str,
+{
+    let mut length = 0;
+    let mut current = None;
+    for item in items {
+        let filename = get_filename(item);
+        let Ok(obj_cidr) = IpCidr::from_str(filename.replace('_', "/").as_str()) else {
+            eprintln!("Failed to parse object '{}'", filename);
+            continue;
+        };
+        if is_in_subnet(&obj_cidr, search_cidr) && (current.is_none() || obj_cidr.network_length() > length) {
+            length = obj_cidr.network_length();
+            current = Some(item);
         }
     }
+    current
+}
+
+pub(in crate::modules) fn normalize_asn(asn: &str) -> BoxResult<String> {
+    let trimmed = asn.trim();
+    let number = if trimmed.len() > 2 && trimmed[..2].eq_ignore_ascii_case("AS") {
+        &trimmed[2..]
+    } else {
+        trimmed
+    };
+    let number = number.parse::<u32>().map_err(|_| format!("Invalid ASN: '{}'", asn))?;
+    Ok(format!("AS{}", number))
+}
+
+pub(in crate::modules) fn find_by_asn(registry_root: &Path, asn: &str) -> BoxResult<Vec<FoundObject>> {
+    let asn = normalize_asn(asn)?;
+    let mut result = Vec::new();
+
+    let aut_num_path = registry_root.join("data/aut-num").join(&asn);
+    if aut_num_path.is_file() {
+        result.push(FoundObject {
+            category: String::from("aut-num"),
+            object: RegistryObject { key_value: read_registry_object_kv(&aut_num_path)?, filename: asn.clone() },
+        });
+    }
 
-    if let Some(current) = current {
-        let target_path = Path::new(registry_root).join(sub_path).join(current);
-        let key_value = read_registry_object_kv::<SimpleObjectLine>(target_path.as_path())?;
-        Ok(serde_json::to_string(&key_value)? + "\n")
+    let categories = [
+        (String::from("route"), String::from("route")),
+        (String::from("route6"), String::from("route6")),
+        (String::from("as-set"), String::from("as-set")),
+    ];
+    result.extend(find_in_categories(registry_root, &categories, |category, object| {
+        let key = if category == "as-set" { "members" } else { "origin" };
+        has_value(object, key, |v| v.eq_ignore_ascii_case(&asn))
+    })?);
+    Ok(result)
+}
+
+pub(in crate::modules) fn find_by_mnt(registry_root: &Path, mnt: &str) -> BoxResult<Vec<FoundObject>> {
+    let categories = get_schema_categories(registry_root)?;
+    find_in_categories(registry_root, &categories, |_, object| {
+        has_value(object, "mnt-by", |v| v.eq_ignore_ascii_case(mnt))
+    })
+}
+
+pub(in crate::modules) fn find_by_person(registry_root: &Path, nic_hdl: &str) -> BoxResult<Vec<FoundObject>> {
+    let categories = get_schema_categories(registry_root)?;
+    find_in_categories(registry_root, &categories, |_, object| {
+        ["admin-c", "tech-c"].iter().any(|key| {
+            has_value(object, key, |v| v.eq_ignore_ascii_case(nic_hdl))
+        })
+    })
+}
+
+pub(in crate::modules) fn find_by_prefix(registry_root: &Path, prefix: &str) -> BoxResult<Vec<FoundObject>> {
+    let search_cidr = IpCidr::from_str(prefix).map_err(|e| format!("Invalid prefix '{}': {}", prefix, e))?;
+    let categories: [(String, String); 2] = if search_cidr.is_ipv4() {
+        [(String::from("inetnum"), String::from("inetnum")), (String::from("route"), String::from("route"))]
     } else {
-        Err("Failed to find target".into())
+        [(String::from("inet6num"), String::from("inet6num")), (String::from("route6"), String::from("route6"))]
+    };
+
+    let mut result = find_in_categories(registry_root, &categories, |_, object| {
+        let Ok(obj_cidr) = IpCidr::from_str(&object.filename.replace('_', "/")) else {
+            return false;
+        };
+        // Covering or covered by the searched prefix
+        is_in_subnet(&obj_cidr, &search_cidr) || is_in_subnet(&search_cidr, &obj_cidr)
+    })?;
+    result.sort_by_key(|x| {
+        let length = IpCidr::from_str(&x.object.filename.replace('_', "/"))
+            .map(|c| c.network_length()).unwrap_or_default();
+        (x.category.clone(), length)
+    });
+    Ok(result)
+}
+
+pub(in crate::modules) fn find_by_attr(registry_root: &Path, key: &str, value_pattern: &str) -> BoxResult<Vec<FoundObject>> {
+    let value_regex = Regex::new(value_pattern)
+        .map_err(|e| format!("Invalid value regex '{}': {}", value_pattern, e))?;
+    let categories = get_schema_categories(registry_root)?;
+    find_in_categories(registry_root, &categories, |_, object| {
+        has_value(object, key, |v| value_regex.is_match(v))
+    })
+}
+
+/// Render objects in the format used by whois servers
+pub(in crate::modules) fn format_whois<T: ObjectLine>(objects: &[(&str, &RegistryObject<T>)]) -> String {
+    if objects.is_empty() {
+        return String::from("%ERROR:101: no entries found\n");
+    }
+    let mut output = String::new();
+    for (category, object) in objects {
+        output.push_str(&format!("% Information related to 'data/{}/{}':\n", category, object.filename));
+        output.push_str(&object_to_text(&object.key_value.0));
+        output.push('\n');
+    }
+    output
+}
+
+/// Render the key-value pairs of an object in the registry file format
+pub(in crate::modules) fn object_to_text<T: ObjectLine>(key_value: &[(String, Vec<T>)]) -> String {
+    let mut output = String::new();
+    for (key, values) in key_value {
+        for value in values {
+            let value = value.get_line_value();
+            let mut lines = value.split('\n');
+            output.push_str(&format!("{:<20}{}\n", format!("{}:", key), lines.next().unwrap_or_default()));
+            for line in lines {
+                if line.is_empty() {
+                    output.push_str("+\n");
+                } else {
+                    output.push_str(&format!("{:<20}{}\n", "", line));
+                }
+            }
+        }
+    }
+    output
+}
+
+fn get_schema_categories(registry_root: &Path) -> BoxResult<Vec<(String, String)>> {
+    let mut categories: Vec<(String, String)> = print_warnings(parse_registry_schema(registry_root, false)?)
+        .into_iter()
+        .map(|s| (s.schema_ref, s.dir_name))
+        .collect();
+    categories.sort();
+    Ok(categories)
+}
+
+fn find_in_categories<F>(registry_root: &Path, categories: &[(String, String)], filter: F) -> BoxResult<Vec<FoundObject>>
+where
+    F: Fn(&str, &RegistryObject<SimpleObjectLine>) -> bool,
+{
+    let mut result = Vec::new();
+    for (category, dir_name) in categories {
+        let sub_path = PathBuf::from("data/").join(dir_name);
+        if !registry_root.join(&sub_path).is_dir() {
+            eprintln!("Error accessing directory referred to by schema: {}", dir_name);
+            continue;
+        }
+        let objects = read_registry_objects::<SimpleObjectLine>(registry_root, &sub_path, false)
+            .map_err(|e| format!("Error reading objects in {}: {}", sub_path.display(), e))?;
+        let mut matching: Vec<_> = objects.into_iter()
+            .filter(|x| filter(category, x))
+            .map(|object| FoundObject { category: category.clone(), object })
+            .collect();
+        matching.sort_by(|a, b| a.object.filename.cmp(&b.object.filename));
+        result.append(&mut matching);
     }
+    Ok(result)
+}
+
+fn has_value<F: Fn(&str) -> bool>(object: &RegistryObject<SimpleObjectLine>, key: &str, predicate: F) -> bool {
+    object.key_value.get(key).is_some_and(|values| {
+        values.iter().any(|v| predicate(v.trim()))
+    })
+}
+
+fn is_in_subnet(target: &IpCidr, test: &IpCidr) -> bool {
+    target.contains(&test.first_address()) && target.contains(&test.last_address())
 }
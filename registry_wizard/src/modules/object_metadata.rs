@@ -1,4 +1,4 @@
-use crate::modules::object_reader::{registry_objects_to_iter, RegistryObjectIterator, SimpleObjectLine};
+use registry_core::object_reader::{registry_objects_to_iter, RegistryObjectIterator, SimpleObjectLine};
 use crate::modules::util::BoxResult;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
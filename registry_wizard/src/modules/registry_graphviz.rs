@@ -1,57 +1,51 @@
-use crate::modules::registry_graph::{LinkInfoSchemaKey, LinkedRegistryObject};
+use registry_core::registry_graph::{LinkInfoSchemaKey, NodeId, RegistryGraph};
 use crate::modules::util::BoxResult;
 use std::borrow::Cow;
-use std::rc::Rc;
-use crate::modules::object_reader::SimpleObjectLine;
+use std::collections::HashSet;
+use registry_core::object_reader::SimpleObjectLine;
 
-type Nd = Rc<LinkedRegistryObject<(), SimpleObjectLine, LinkInfoSchemaKey>>;
+type Nd = NodeId;
 type Ed = (Nd, Nd);
-struct Graph {
+struct Graph<'g> {
+    graph: &'g RegistryGraph<SimpleObjectLine, LinkInfoSchemaKey>,
     nodes: Vec<Nd>,
     mnt: Option<String>
 }
 
-impl<'a> dot::GraphWalk<'a, Nd, Ed > for Graph {
+impl<'a> dot::GraphWalk<'a, Nd, Ed > for Graph<'_> {
     fn nodes(&'a self) -> dot::Nodes<'a, Nd> {
         Cow::Borrowed(&self.nodes[..])
     }
 
     fn edges(&'a self) -> dot::Edges<'a, Ed>  {
         let mut edges: Vec<Ed> = Vec::new();
+        let included: HashSet<_> = self.nodes.iter().collect();
         for node in &self.nodes {
-            let links: Vec<_> = node.get_forward_links()
-                .chain(node.get_back_links()).collect();
-            for link in links {
-                let mut found = false;
-                for item in self.nodes().iter() {
-                    if Rc::ptr_eq(item, &link.1) {
-                        found = true;
-                        break;
-                    }
-                }
-                if !found {
+            let object = self.graph.node(*node);
+            for link in object.get_forward_links().iter().chain(object.get_back_links()) {
+                if !included.contains(&link.1) {
                     continue;
                 }
-                edges.push((node.clone(), link.1));
+                edges.push((*node, link.1));
             }
         }
         Cow::Owned(edges)
     }
 
     fn source(&'a self, edge: &Ed) -> Nd {
-        edge.0.clone()
+        edge.0
     }
 
     fn target(&'a self, edge: &Ed) -> Nd {
-        edge.1.clone()
+        edge.1
     }
 }
 
-impl<'a> dot::Labeller<'a, Nd, Ed> for Graph {
+impl<'a> dot::Labeller<'a, Nd, Ed> for Graph<'_> {
     fn graph_id(&'a self) -> dot::Id<'a> { dot::Id::new("graph1").unwrap() }
 
     fn node_id(&'a self, n: &Nd) -> dot::Id<'a> {
-        let name = format!("{}/{}", &n.schema_ref ,&n.object.filename);
+        let name = self.graph.path(*n);
         let f = name.as_bytes().iter().fold(String::new(), |mut acc, &x| {
             acc.push_str(&format!("{:02x}", x));
             acc
@@ -61,10 +55,10 @@ impl<'a> dot::Labeller<'a, Nd, Ed> for Graph {
         dot::Id::new(id_str).unwrap()
     }
     fn node_label(&'a self, n: &Nd) -> dot::LabelText<'a> {
-        dot::LabelText::LabelStr(format!("{}/{}", n.schema_ref, n.object.filename).into())
+        dot::LabelText::LabelStr(self.graph.path(*n).into())
     }
     fn node_color(&'a self, n: &Nd) -> Option<dot::LabelText<'a>> {
-        n.object.key_value.get("mnt-by").and_then(|mnt_list| {
+        self.graph.node(*n).object.key_value.get("mnt-by").and_then(|mnt_list| {
             if mnt_list.contains(self.mnt.as_ref()?) {
                return Some(dot::LabelText::LabelStr("red".into()));
             }
@@ -74,8 +68,8 @@ impl<'a> dot::Labeller<'a, Nd, Ed> for Graph {
 
 }
 
-pub fn create_graphviz(a: Vec<Nd>, mnt: Option<String>) -> BoxResult<String> {
+pub fn create_graphviz(graph: &RegistryGraph<SimpleObjectLine, LinkInfoSchemaKey>, a: Vec<Nd>, mnt: Option<String>) -> BoxResult<String> {
     let mut buffer = Vec::new();
-    dot::render(&Graph{nodes: a, mnt}, &mut buffer)?;
+    dot::render(&Graph{graph, nodes: a, mnt}, &mut buffer)?;
     Ok(String::from_utf8(buffer)?)
 }
\ No newline at end of file
@@ -1,17 +1,19 @@
-use crate::modules::object_reader::OrderedObjectLine;
-use crate::modules::registry_graph::{create_registry_graph, link_visit, parse_registry_schema, ExtraDataTrait, LinkInfoLineNumberOnly, LinkedRegistryObject, WEAKLY_REFERENCING};
-use crate::modules::util::{get_item_list, BoxResult, EitherOr};
-use serde::Serialize;
-use std::cell::Cell;
+use registry_core::object_reader::OrderedObjectLine;
+use registry_core::registry_graph::{create_registry_graph, LinkInfoLineNumberOnly, NodeId, ServerGraph, WEAKLY_REFERENCING};
+use registry_core::schema::{parse_registry_schema, Schema};
+use registry_core::traversal::VisitedObjects;
+use crate::modules::util::{get_item_list, print_warnings, BoxResult, EitherOr};
 use std::path::Path;
-use std::rc::Rc;
 
-#[derive(Debug, Serialize, Default)]
+/// Category of the registry object, which nearly every object refers to with `source`
+const REGISTRY_CATEGORY: &str = "registry";
+
+/// State of a graph node during the removal, indexed by its `NodeId`
+#[derive(Debug, Default, Clone)]
 struct MetaData {
-    marked: Cell<bool>,
-    deleted: Cell<bool>,
+    marked: bool,
+    deleted: bool,
 }
-impl ExtraDataTrait for MetaData {}
 
 pub enum RemovalCategory {
     Mnt,
@@ -27,6 +29,21 @@ impl RemovalCategory {
     }
 }
 
+/// A change to the registry needed to remove the objects
+pub(in crate::modules) enum RemovalAction {
+    /// Remove the object file (path relative to the registry root)
+    RemoveObject(String),
+    /// Delete the (1-based) lines of the object file referencing removed objects
+    DeleteLines(String, Vec<usize>),
+}
+
+/// The changes needed to remove the objects, along with what the user should check
+pub(in crate::modules) struct RemovalPlan {
+    pub actions: Vec<RemovalAction>,
+    /// Conflicts needing a manual review and removed sub-graphs
+    pub warnings: Vec<String>,
+}
+
 pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
               removal_category: RemovalCategory,
               with_subgraph_check: bool) -> BoxResult<String> {
@@ -36,59 +53,113 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
 
     let raw_list = get_item_list(data_input)?;
 
-    let mut output = String::new();
-    let registry_schema = parse_registry_schema(registry_root, true)?;
-    let graph = create_registry_graph::<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>(registry_root, &registry_schema, true, false)?;
-
-    let removal_list: Vec<String>;
-    let affected_graph;
-    match removal_category {
+    let removal_list: Vec<String> = match removal_category {
         RemovalCategory::Mnt => {
-            removal_list = raw_list.split(",").map(String::from).collect();
-            affected_graph = graph.get("mntner").ok_or("mntner graph not found")?;
+            raw_list.split(",").map(String::from).collect()
         }
         RemovalCategory::Asn => {
             let ok = raw_list.chars().all(|c|c == ',' || char::is_numeric(c) || char::is_whitespace(c));
             if !ok {
                 return Err("ASN list contains invalid characters".into());
             }
-            affected_graph = graph.get("aut-num").ok_or("aut-num graph not found")?;
-            removal_list = raw_list.split(",").map(String::from)
-                .map(|x| format!("AS{}", x.trim())).collect();
+            raw_list.split(",").map(String::from)
+                .map(|x| format!("AS{}", x.trim())).collect()
         }
+    };
+
+    let registry_schema = print_warnings(parse_registry_schema(registry_root, false)?);
+    let graph: ServerGraph = print_warnings(create_registry_graph(registry_root, &registry_schema, true, true)?);
+    eprintln!("Provided list contains {} object(s)", removal_list.len());
+    eprintln!("Analyzing dependency graph");
+    let plan = plan_removal(&graph, &registry_schema, &removal_list, removal_category, with_subgraph_check)?;
+    for warning in &plan.warnings {
+        eprintln!("{}", warning);
     }
 
+    let mut output = String::new();
+    for action in plan.actions {
+        match action {
+            RemovalAction::RemoveObject(path) => {
+                output.push_str(&format!("rm '{}'\n", path));
+            }
+            RemovalAction::DeleteLines(path, line_numbers) => {
+                let line_string = line_numbers.iter().map(|x| {
+                    let mut str = x.to_string();
+                    str.push('d');
+                    str
+                }).collect::<Vec<String>>().join(";");
+                output.push_str(&format!("sed -i '{}' '{}'\n", line_string, path));
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Links of the object that tie objects together for the removal. References to the object itself and to the
+/// registry object are left out, as nearly every object has them.
+fn forward_links(graph: &ServerGraph, id: NodeId) -> impl Iterator<Item = &(LinkInfoLineNumberOnly, NodeId)> {
+    graph.node(id).get_forward_links().iter()
+        .filter(move |x| x.1 != id && graph.node(x.1).schema_ref != REGISTRY_CATEGORY)
+}
+
+fn back_links(graph: &ServerGraph, id: NodeId) -> &[(LinkInfoLineNumberOnly, NodeId)] {
+    let node = graph.node(id);
+    if node.schema_ref == REGISTRY_CATEGORY {
+        &[]
+    } else {
+        node.get_back_links()
+    }
+}
+
+/// Mark the objects linked to the object as visited and queue those not visited before, like `link_visit` but
+/// following only the links of the removal
+fn visit_links(graph: &ServerGraph, id: NodeId, visited: &mut VisitedObjects, to_visit: &mut Vec<NodeId>) {
+    for link in forward_links(graph, id).chain(back_links(graph, id)) {
+        if visited.insert(link.1) {
+            to_visit.push(link.1);
+        }
+    }
+}
+
+/// Determine the changes needed to remove the mntner/aut-num objects along with all their dependencies
+pub(in crate::modules) fn plan_removal(graph: &ServerGraph, registry_schema: &[Schema], removal_list: &[String],
+                                       removal_category: RemovalCategory,
+                                       with_subgraph_check: bool) -> BoxResult<RemovalPlan> {
+    let mut actions = Vec::new();
+    let mut warnings = Vec::new();
+    let mut meta = vec![MetaData::default(); graph.node_count()];
+
+    let affected_graph = match removal_category {
+        RemovalCategory::Mnt => graph.category("mntner").ok_or("mntner graph not found")?,
+        RemovalCategory::Asn => graph.category("aut-num").ok_or("aut-num graph not found")?,
+    };
+
     let only_one_removal_item = matches!(removal_list.len(), 1);
-    eprintln!("Provided list contains {} object(s)", removal_list.len());
 
     // Assuming the registry objects form an undirected graph which is a superset of many disconnected sub-graphs
     // Mark all mntner/aut-num vertices to delete
-    eprintln!("Analyzing dependency graph (1/6)");
-    for t in affected_graph {
-        let t = t.clone();
-        if removal_list.contains(&t.object.filename) {
-            t.extra.marked.set(true);
+    for &t in affected_graph {
+        if removal_list.contains(&graph.node(t).object.filename) {
+            meta[t].marked = true;
         }
     }
 
     // Ensure DN42-MNT is not marked
-    graph.get("mntner").ok_or("mntner graph not found")?.iter()
-        .find(|x| x.object.filename == "DN42-MNT")
-        .ok_or("DN42-MNT not found")?.extra.marked.set(false);
+    graph.category("mntner").ok_or("mntner graph not found")?;
+    meta[graph.find("mntner", "DN42-MNT").ok_or("DN42-MNT not found")?].marked = false;
 
-    eprintln!("Analyzing dependency graph (2/6)");
     // For every *marked* vertex
-    for t in affected_graph {
-        if !t.extra.marked.get() {
+    for &t_id in affected_graph {
+        if !meta[t_id].marked {
             continue;
         }
+        let t = graph.node(t_id);
         // Recursively follow each path while keeping track of visited vertices
-        let mut visited: Vec<Rc<LinkedRegistryObject<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>>> = Vec::new();
-        let mut to_visit: Vec<Rc<LinkedRegistryObject<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>>> = Vec::new();
-        visited.push(t.clone());
-        to_visit.push(t.clone());
+        let mut visited = VisitedObjects::new(t_id);
+        let mut to_visit = vec![t_id];
 
-        while let Some(obj) = to_visit.pop() {
+        while let Some(id) = to_visit.pop() {
+            let obj = graph.node(id);
             if WEAKLY_REFERENCING.contains(&obj.schema_ref.as_str()) {
                 continue;
             }
@@ -99,39 +170,36 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
 
             // If an *unmarked* mntner/aut-num vertex is encountered, unmark self and flag for manual review
             let empty_vec : Vec<OrderedObjectLine> = Vec::with_capacity(0);
-            if !obj.extra.marked.get() && obj.schema_ref == removal_category.as_str() {
-                t.extra.marked.set(false);
+            if !meta[id].marked && obj.schema_ref == removal_category.as_str() {
+                meta[t_id].marked = false;
                 let t_mnt = t.object.key_value.get("mnt-by").unwrap_or(&empty_vec);
-                if !t_mnt.iter().map(|x| &x.1).collect::<Vec<_>>().contains(&&String::from("DN42-MNT")) || only_one_removal_item {
-                    eprintln!("Manual review: {} - {:?} (First conflict with active object: {} - {:?})",
-                              t.object.filename, t_mnt,
-                              obj.object.filename, obj.object.key_value.get("mnt-by").unwrap_or(&empty_vec)
-                    );
-                }
+                let message = format!("Manual review: {} - {:?} (First conflict with active object: {} - {:?})",
+                                      t.object.filename, t_mnt,
+                                      obj.object.filename, obj.object.key_value.get("mnt-by").unwrap_or(&empty_vec));
                 if only_one_removal_item && !with_subgraph_check {
-                    return Err("Manual review needed".into());
+                    return Err(format!("{}\nManual review needed", message).into());
+                }
+                if !t_mnt.iter().map(|x| &x.1).collect::<Vec<_>>().contains(&&String::from("DN42-MNT")) || only_one_removal_item {
+                    warnings.push(message);
                 }
                 break;
             }
 
-            link_visit(&obj, &mut visited, &mut to_visit);
+            visit_links(graph, id, &mut visited, &mut to_visit);
         }
     }
 
-
-    eprintln!("Analyzing dependency graph (3/6)");
     // For every *still marked* mntner/aut-num vertex: Recursively delete all vertices
     // Recursively follow each path while keeping track of visited vertices
-    for t in affected_graph {
-        if !t.extra.marked.get() {
+    for &t_id in affected_graph {
+        if !meta[t_id].marked {
             continue;
         }
-        let mut visited: Vec<Rc<LinkedRegistryObject<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>>> = Vec::new();
-        let mut to_visit: Vec<Rc<LinkedRegistryObject<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>>> = Vec::new();
-        visited.push(t.clone());
-        to_visit.push(t.clone());
+        let mut visited = VisitedObjects::new(t_id);
+        let mut to_visit = vec![t_id];
 
-        while let Some(obj) = to_visit.pop() {
+        while let Some(id) = to_visit.pop() {
+            let obj = graph.node(id);
             if WEAKLY_REFERENCING.contains(&obj.schema_ref.as_str()) {
                 continue;
             }
@@ -139,51 +207,46 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
                 // Special case
                 continue;
             }
-            if obj.extra.deleted.get() {
+            if meta[id].deleted {
                 continue;
             }
-            obj.extra.deleted.set(true);
-            output.push_str(&format!("rm 'data/{}/{}'\n", obj.data_dir, obj.object.filename));
+            meta[id].deleted = true;
+            actions.push(RemovalAction::RemoveObject(format!("data/{}/{}", obj.data_dir, obj.object.filename)));
 
-            link_visit(&obj, &mut visited, &mut to_visit);
+            visit_links(graph, id, &mut visited, &mut to_visit);
         }
     }
 
-    eprintln!("Analyzing dependency graph (4/6)");
     // Check if weakly referenced objects have dangling references
     for w in WEAKLY_REFERENCING {
-        let empty_vec = Vec::new();
-        let w_list: Vec<_> = graph.get(w)
-            .unwrap_or(&empty_vec)
-            .iter().collect();
-        for w_item in w_list {
+        for &w_id in graph.category(w).unwrap_or_default() {
+            let w_item = graph.node(w_id);
             let mut found = false;
-            for reference in w_item.get_back_links()
-                .chain(w_item.get_forward_links()) {
-                if reference.1.extra.deleted.get() {
+            for reference in back_links(graph, w_id).iter()
+                .chain(forward_links(graph, w_id)) {
+                if meta[reference.1].deleted {
                     continue;
                 }
                 found = true;
             }
             if !found {
-                w_item.extra.deleted.set(true);
-                output.push_str(&format!("rm 'data/{}/{}'\n", w_item.data_dir, w_item.object.filename));
+                meta[w_id].deleted = true;
+                actions.push(RemovalAction::RemoveObject(format!("data/{}/{}", w_item.data_dir, w_item.object.filename)));
                 continue;
             }
         }
     }
 
-    eprintln!("Analyzing dependency graph (5/6)");
     // Check for remaining dangling references
-    for item in graph.values().flatten() {
-        if item.extra.deleted.get() {
+    for (item_id, item) in graph.nodes() {
+        if meta[item_id].deleted {
             continue;
         }
 
         let mut has_links = false;
         let mut line_numbers = Vec::new();
-        for link in item.get_forward_links() {
-            if !link.1.extra.deleted.get() {
+        for link in forward_links(graph, item_id) {
+            if !meta[link.1].deleted {
                 has_links = true;
                 continue;
             }
@@ -191,18 +254,13 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
             line_numbers.push(link.0 + 1);
         }
         if !line_numbers.is_empty() {
-            let line_string = line_numbers.iter().map(|x| {
-                let mut str = x.to_string();
-                str.push('d');
-                str
-            }).collect::<Vec<String>>().join(";");
-            output.push_str(&format!("sed -i '{}' 'data/{}/{}'\n", line_string, item.data_dir, item.object.filename));
+            actions.push(RemovalAction::DeleteLines(format!("data/{}/{}", item.data_dir, item.object.filename), line_numbers));
         }
 
 
         if !has_links {
-            for link in item.get_back_links() {
-                if !link.1.extra.deleted.get() {
+            for link in back_links(graph, item_id) {
+                if !meta[link.1].deleted {
                     has_links = true;
                     break;
                 }
@@ -210,29 +268,29 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
         }
 
         if !has_links {
-            item.extra.deleted.set(true);
-            output.push_str(&format!("rm 'data/{}/{}'\n", item.data_dir, item.object.filename));
+            meta[item_id].deleted = true;
+            actions.push(RemovalAction::RemoveObject(format!("data/{}/{}", item.data_dir, item.object.filename)));
             continue;
         }
     }
 
-    eprintln!("Analyzing dependency graph (6/6)");
     // Final pass
     // Check if all required lookup keys are present (important for weakly referencing objects)
-    for item in graph.values().flatten() {
-        if item.extra.deleted.get() {
+    for (item_id, item) in graph.nodes() {
+        if meta[item_id].deleted {
             continue;
         }
 
         let applicable_schema = &registry_schema.iter().find(|x| x.schema_ref == item.schema_ref);
         if applicable_schema.is_none() {
-            eprintln!("Warning: can't find schema for category '{}'", item.schema_ref);
+            warnings.push(format!("Warning: can't find schema for category '{}'", item.schema_ref));
             continue;
         }
         let required_categories = applicable_schema.unwrap()
             .keys.iter()
             .filter(|x| x.required && !x.lookup_targets.is_empty())
             .flat_map(|x| x.lookup_targets.iter())
+            .filter(|x| *x != REGISTRY_CATEGORY)
             .collect::<Vec<_>>();
         let mut required_category_missing = false;
         for required_category in required_categories {
@@ -240,41 +298,40 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
                 // We have that category
                 continue;
             }
-            if !item.get_forward_links()
-                .filter(|x| !x.1.extra.deleted.get())
-                .any(|x| x.1.schema_ref == *required_category) {
+            if !forward_links(graph, item_id)
+                .filter(|x| !meta[x.1].deleted)
+                .any(|x| graph.node(x.1).schema_ref == *required_category) {
                 // If we don't find a link with the required category
                 required_category_missing = true;
                 break;
             }
         }
         if required_category_missing {
-            item.extra.deleted.set(true);
-            output.push_str(&format!("rm 'data/{}/{}'\n", item.data_dir, item.object.filename));
+            meta[item_id].deleted = true;
+            actions.push(RemovalAction::RemoveObject(format!("data/{}/{}", item.data_dir, item.object.filename)));
             continue;
         }
     }
 
     if !with_subgraph_check {
-        return Ok(output);
+        return Ok(RemovalPlan { actions, warnings });
     }
 
-    eprintln!("Checking for invalid sub-graphs");
     // Check for incomplete sub-graphs
-    for item in graph.get("mntner").ok_or("can't find mntner category")? {
-        if item.extra.deleted.get() {
+    for &item_id in graph.category("mntner").ok_or("can't find mntner category")? {
+        if meta[item_id].deleted {
             continue;
         }
+        let item = graph.node(item_id);
 
         let mut graph_has_asn = false;
 
-        let mut visited: Vec<Rc<LinkedRegistryObject<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>>> = Vec::new();
-        let mut to_visit: Vec<Rc<LinkedRegistryObject<MetaData, OrderedObjectLine, LinkInfoLineNumberOnly>>> = Vec::new();
-        visited.push(item.clone());
-        to_visit.push(item.clone());
+        let mut visited = VisitedObjects::new(item_id);
+        let mut to_visit = vec![item_id];
 
-        while let Some(obj) = to_visit.pop() {
-            if obj.extra.deleted.get() {
+        while let Some(id) = to_visit.pop() {
+            let obj = graph.node(id);
+            if meta[id].deleted {
                 continue;
             }
             if obj.schema_ref == "aut-num" {
@@ -289,18 +346,21 @@ pub fn output(registry_root: &Path, data_input: EitherOr<String, String>,
                 break;
             }
 
-            link_visit(&obj, &mut visited, &mut to_visit);
+            visit_links(graph, id, &mut visited, &mut to_visit);
         }
         if !graph_has_asn {
-            eprintln!("Warning: Deleting invalid sub-graph for item '{}': {:?}", item.object.filename,
-                      visited.iter().map(|x| x.object.filename.clone()).collect::<Vec<_>>());
-            for visited in &visited.iter()
-                .filter(|x| !x.extra.deleted.get()).collect::<Vec<_>>() {
-                visited.extra.deleted.set(true);
-                output.push_str(&format!("rm 'data/{}/{}'\n", visited.data_dir, visited.object.filename));
+            warnings.push(format!("Warning: Deleting invalid sub-graph for item '{}': {:?}", item.object.filename,
+                                  visited.list.iter().map(|x| graph.node(*x).object.filename.clone()).collect::<Vec<_>>()));
+            for &visited in &visited.list {
+                if meta[visited].deleted {
+                    continue;
+                }
+                meta[visited].deleted = true;
+                let visited = graph.node(visited);
+                actions.push(RemovalAction::RemoveObject(format!("data/{}/{}", visited.data_dir, visited.object.filename)));
             }
         }
     }
 
-    Ok(output)
+    Ok(RemovalPlan { actions, warnings })
 }
\ No newline at end of file
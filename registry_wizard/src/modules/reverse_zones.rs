@@ -0,0 +1,177 @@
+use registry_core::object_reader::{ObjectLine, RegistryObject};
+use crate::modules::util::BoxResult;
+use crate::modules::zone_builder::{check_nserver_names, fqdn, object_values, parse_ds_rdata_list, parse_nserver, read_zone_objects, report_invalid, write_zones, zone_serial, DsRecord, RecordData, Zone, ZoneObjects};
+use crate::modules::zone_files::STATIC_ENTRIES;
+use cidr_utils::cidr::IpCidr;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub fn output(registry_root: &Path, zone_filter: Vec<String>, output_dir: Option<PathBuf>, strict: bool) -> BoxResult<String> {
+    let inetnum = read_zone_objects(registry_root, "inetnum")?;
+    let inet6num = read_zone_objects(registry_root, "inet6num")?;
+    let zones = build_reverse_zones(&ZoneObjects::new(zone_serial(registry_root), &[], &inetnum, &inet6num), strict)?;
+    write_zones(zones, &zone_filter, output_dir)
+}
+
+struct Delegation {
+    cidr: IpCidr,
+    filename: String,
+    n_server: Vec<String>,
+    ds_rdata: Vec<DsRecord>,
+}
+
+/// Build the reverse zones for all dn42 parent prefixes, delegating each inetnum / inet6num with nameservers.
+/// Invalid objects abort in strict mode and are skipped otherwise.
+pub(in crate::modules) fn build_reverse_zones<T: ObjectLine>(objects: &ZoneObjects<T>, strict: bool) -> BoxResult<Vec<Zone>> {
+    let v4_delegations = read_delegations(&objects.inetnum, "data/inetnum", strict)?;
+    let v6_delegations = read_delegations(&objects.inet6num, "data/inet6num", strict)?;
+
+    let mut zones = Vec::new();
+    for (zone_name, parent_file) in STATIC_ENTRIES {
+        let (parent_name, parent_cidr) = parent_file.split_once('/')
+            .and_then(|(_, name)| Some((name, IpCidr::from_str(&name.replace('_', "/")).ok()?)))
+            .ok_or(format!("Invalid parent prefix: {}", parent_file))?;
+        let parent_objects = if parent_cidr.is_ipv4() { &objects.inetnum } else { &objects.inet6num };
+        let parent = parent_objects.iter().find(|x| x.filename == parent_name)
+            .ok_or(format!("Parent object data/{} not found", parent_file))?;
+        let parent_n_server = object_values(parent, "nserver");
+        if let Err(e) = check_nserver_names(&parent_n_server) {
+            report_invalid(format!("data/{}: {}, skipping parent zone '{}'", parent_file, e, zone_name), strict)?;
+            continue;
+        }
+        let apex_nameservers: Vec<String> = parent_n_server.iter().map(|x| parse_nserver(x).0).collect();
+        if apex_nameservers.is_empty() {
+            report_invalid(format!("no nameservers found for parent zone '{}', skipping", zone_name), strict)?;
+            continue;
+        }
+
+        let delegations = if parent_cidr.is_ipv4() { &v4_delegations } else { &v6_delegations };
+        let mut zone = Zone::new(zone_name, objects.serial, &apex_nameservers);
+        let zone_delegations: Vec<&Delegation> = delegations.iter()
+            .filter(|d| d.cidr != parent_cidr && is_in_subnet(&parent_cidr, &d.cidr))
+            .collect();
+        for delegation in &zone_delegations {
+            // Delegations inside a more specific delegation belong to the child zone
+            if zone_delegations.iter().any(|other| other.cidr != delegation.cidr && is_in_subnet(&other.cidr, &delegation.cidr)) {
+                continue;
+            }
+            add_delegation(&mut zone, delegation);
+        }
+        zones.push(zone);
+    }
+    Ok(zones)
+}
+
+fn read_delegations<T: ObjectLine>(objects: &[&RegistryObject<T>], sub_path: &str, strict: bool) -> BoxResult<Vec<Delegation>> {
+    let mut delegations = Vec::new();
+    for object in objects {
+        let n_server = object_values(object, "nserver");
+        if n_server.is_empty() {
+            continue;
+        }
+        let name = format!("{}/{}", sub_path, object.filename);
+        let Ok(cidr) = IpCidr::from_str(&object.filename.replace('_', "/")) else {
+            report_invalid(format!("Failed to parse object '{}'", name), strict)?;
+            continue;
+        };
+        if let Err(e) = check_nserver_names(&n_server) {
+            report_invalid(format!("{}: {}", name, e), strict)?;
+            continue;
+        }
+        let ds_rdata = parse_ds_rdata_list(&object_values(object, "ds-rdata"), &object.filename, strict)
+            .map_err(|e| format!("{}: {}", name, e))?;
+        delegations.push(Delegation {
+            cidr,
+            filename: object.filename.clone(),
+            n_server,
+            ds_rdata,
+        });
+    }
+    delegations.sort_by(|a, b| a.filename.cmp(&b.filename));
+    Ok(delegations)
+}
+
+fn add_delegation(zone: &mut Zone, delegation: &Delegation) {
+    let zone_cuts = match delegation.cidr {
+        IpCidr::V4(_) if delegation.cidr.network_length() > 24 => {
+            add_classless_v4_aliases(zone, &delegation.cidr)
+        }
+        _ => get_zone_cuts(&delegation.cidr),
+    };
+
+    for zone_cut in zone_cuts {
+        for n_server in &delegation.n_server {
+            let (host, address) = parse_nserver(n_server);
+            if host.is_empty() {
+                continue;
+            }
+            zone.add(&zone_cut, RecordData::Ns(fqdn(&host)));
+            if let Some(address) = address {
+                zone.add_glue(&host, address);
+            }
+        }
+        for ds in &delegation.ds_rdata {
+            zone.add(&zone_cut, RecordData::Ds(ds.clone()));
+        }
+    }
+}
+
+/// Get the zone names for a prefix, splitting it up to the next octet (v4) or nibble (v6) boundary
+fn get_zone_cuts(cidr: &IpCidr) -> Vec<String> {
+    let length = cidr.network_length() as u32;
+    let (unit, total_bits, suffix) = if cidr.is_ipv4() {
+        (8, 32, "in-addr.arpa")
+    } else {
+        (4, 128, "ip6.arpa")
+    };
+    let boundary = length.div_ceil(unit) * unit;
+    let labels = (boundary / unit) as usize;
+    let base = ip_to_u128(cidr.first_address());
+
+    let mut zone_cuts = Vec::new();
+    for i in 0..(1u128 << (boundary - length)) {
+        let address = base + (i << (total_bits - boundary));
+        let digits = address_digits(address, unit, total_bits);
+        let mut name: Vec<String> = digits[..labels].iter().rev().map(|d| {
+            if unit == 4 { format!("{:x}", d) } else { d.to_string() }
+        }).collect();
+        name.push(suffix.to_string());
+        zone_cuts.push(name.join("."));
+    }
+    zone_cuts
+}
+
+/// RFC 2317 classless delegation: alias each address into the delegated sub-zone and return its name
+fn add_classless_v4_aliases(zone: &mut Zone, cidr: &IpCidr) -> Vec<String> {
+    let length = cidr.network_length();
+    let octets = match cidr.first_address() {
+        IpAddr::V4(v4) => v4.octets(),
+        IpAddr::V6(_) => return Vec::new(),
+    };
+    let parent = format!("{}.{}.{}.in-addr.arpa", octets[2], octets[1], octets[0]);
+    let zone_cut = format!("{}/{}.{}", octets[3], length, parent);
+    let count = 1u16 << (32 - length);
+    for i in 0..count {
+        let host = octets[3] as u16 + i;
+        zone.add(&format!("{}.{}", host, parent), RecordData::Cname(format!("{}.{}.", host, zone_cut)));
+    }
+    vec![zone_cut]
+}
+
+fn address_digits(address: u128, unit: u32, total_bits: u32) -> Vec<u128> {
+    (0..total_bits / unit).map(|i| {
+        (address >> (total_bits - unit * (i + 1))) & ((1 << unit) - 1)
+    }).collect()
+}
+
+fn ip_to_u128(address: IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(v4) => u32::from(v4) as u128,
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+fn is_in_subnet(target: &IpCidr, test: &IpCidr) -> bool {
+    target.contains(&test.first_address()) && target.contains(&test.last_address())
+}
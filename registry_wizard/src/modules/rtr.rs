@@ -7,6 +7,7 @@ use tokio::net::TcpListener;
 
 use crate::modules::util::os_signals::{signal_listener, CustomSignal};
 use crate::modules::util::BoxResult;
+use roa_wizard::RoaData;
 use rpki::resources::{addr::Prefix, Asn, MaxLenPrefix};
 use rpki::rtr::server::{PayloadDiff, PayloadSet};
 use rpki::rtr::{
@@ -49,18 +50,18 @@ impl<T> RecentItems<T> {
 
 type StateWithData = RecentItems<Arc<(State, Vec<RouteOrigin>)>>;
 #[derive(Clone)]
-struct DataState {
+pub(in crate::modules) struct DataState {
     data: Arc<Mutex<StateWithData>>,
 }
 
 impl DataState {
-    fn new() -> Self {
+    pub fn new() -> Self {
         DataState {
             data: Arc::new(Mutex::new(RecentItems::new(4))),
         }
     }
 
-    fn add_data(&self, new: Vec<RouteOrigin>) {
+    pub fn add_data(&self, new: Vec<RouteOrigin>) {
         let mut data = self.data.lock().unwrap();
         let new_state = if let Some(mut latest_state) = data.latest().map(|x| x.clone().0) {
             latest_state.inc();
@@ -73,13 +74,13 @@ impl DataState {
 }
 
 #[derive(Clone)]
-struct VrpSource {
+pub(in crate::modules) struct VrpSource {
     data_state: Arc<DataState>,
     timings: Timing,
 }
 
 impl VrpSource {
-    fn new(data_state: Arc<DataState>, timings: Timing) -> Self {
+    pub fn new(data_state: Arc<DataState>, timings: Timing) -> Self {
         VrpSource {
             data_state,
             timings,
@@ -87,7 +88,7 @@ impl VrpSource {
     }
 }
 
-struct PayloadIterator {
+pub(in crate::modules) struct PayloadIterator {
     latest_data: Arc<(State, Vec<RouteOrigin>)>,
     old_data: Option<Arc<(State, Vec<RouteOrigin>)>>,
     position: usize,
@@ -292,7 +293,7 @@ pub fn start_rtr(
     Ok("".into())
 }
 
-async fn server(
+pub(in crate::modules) async fn server(
     notify: NotifySender,
     vrp_source: VrpSource,
     port: u16,
@@ -339,7 +340,10 @@ fn update_registry_data(registry_root: PathBuf) -> BoxResult<Vec<RouteOrigin>> {
         eprintln!("Warning during ROA data generation: {}", warn);
         roa_wizard::WarningAction::ActionContinue
     }).map_err(|x| format!("Error generating roa: {}", x))?;
+    route_origins(&roa)
+}
 
+pub(in crate::modules) fn route_origins(roa: &RoaData) -> BoxResult<Vec<RouteOrigin>> {
     let mut result = Vec::new();
     for item in roa.object_list().iter() {
         let ip_addr = item.prefix.first_address();
@@ -1,4 +1,3 @@
-use std::error::Error;
 use std::fs::File;
 use std::{fs, io};
 use std::io::BufRead;
@@ -6,10 +5,10 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::SystemTime;
 
-#[cfg(any(feature = "explorer", feature = "rtr-server"))]
+#[cfg(any(feature = "explorer", feature = "rtr-server", feature = "dns-server", feature = "whois-server"))]
 pub mod os_signals;
 
-pub type BoxResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+pub use registry_core::BoxResult;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum EitherOr<X, Y> {
@@ -50,6 +49,14 @@ pub(in crate::modules) fn walk_dir(path: impl AsRef<Path>, max_depth: i32) -> Bo
     Ok(buf)
 }
 
+/// Print the warnings returned along with a result of reading the registry and return the result
+pub(crate) fn print_warnings<T>((result, warnings): (T, Vec<String>)) -> T {
+    for warning in warnings {
+        eprintln!("{}", warning);
+    }
+    result
+}
+
 pub(crate) fn get_item_list(data_input: EitherOr<String,String>) -> BoxResult<String>{
     match data_input {
         EitherOr::A(file) => {
@@ -101,3 +108,16 @@ pub(crate) fn get_git_commit_hash(path: impl AsRef<Path>) -> Option<String> {
     }
     Some(String::from_utf8_lossy(&cmd_output.stdout).trim_end_matches('\n').to_string())
 }
+
+pub(crate) fn get_git_commit_time(path: impl AsRef<Path>) -> Option<u64> {
+    let cmd_output = Command::new("git")
+        .arg("log")
+        .arg("-1")
+        .arg("--format=%ct")
+        .current_dir(path)
+        .output().ok()?;
+    if !cmd_output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&cmd_output.stdout).trim_end_matches('\n').parse::<u64>().ok()
+}
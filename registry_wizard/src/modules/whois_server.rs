@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use cidr_utils::cidr::IpCidr;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use crate::modules::object_finder::{find_most_specific, format_whois, normalize_asn};
+use registry_core::registry_graph::{create_registry_graph, NodeId, ServerGraph};
+use registry_core::schema::parse_registry_schema;
+use crate::modules::util::os_signals::{signal_listener, CustomSignal};
+use crate::modules::util::{get_git_commit_hash, print_warnings, BoxResult};
+
+const MAX_QUERY_LENGTH: u64 = 1024;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+const CONTACT_CATEGORIES: [&str; 2] = ["person", "role"];
+
+#[derive(Default)]
+pub(in crate::modules) struct WhoisData {
+    graph: Arc<ServerGraph>,
+    /// Nodes ordered by category name, which is the order of inverse lookup results
+    objects: Vec<NodeId>,
+    /// The person and role objects referenced by each node
+    contacts: Vec<Vec<NodeId>>,
+    /// Uppercase object name to nodes
+    names: HashMap<String, Vec<NodeId>>,
+    commit_hash: String,
+}
+
+impl WhoisData {
+    /// Nodes and names of the objects of a category
+    fn category(&self, category: &str) -> Vec<(NodeId, &str)> {
+        self.graph.category(category).unwrap_or_default().iter()
+            .map(|id| (*id, self.graph.node(*id).object.filename.as_str()))
+            .collect()
+    }
+}
+
+struct WhoisQuery {
+    recursive: bool,
+    inverse_keys: Vec<String>,
+    term: String,
+}
+
+pub fn start_whois(registry_root: impl AsRef<Path>, port: u16, bind_ip: Option<String>) -> BoxResult<String> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let registry_root = registry_root.as_ref().to_path_buf();
+        let whois_data = Arc::new(RwLock::new(Arc::new(WhoisData::default())));
+        if let Err(err) = update_whois_data(&registry_root, &whois_data) {
+            return Err(format!("Error reading registry data: {}", err));
+        }
+
+        let (sig_chan_tx, mut sig_chan_rx) = broadcast::channel::<CustomSignal>(1);
+        let signal_listener_handle = tokio::spawn(signal_listener(sig_chan_tx.clone()));
+
+        let updater_whois_data = whois_data.clone();
+        let registry_data_updater = tokio::spawn(async move {
+            loop {
+                match sig_chan_rx.recv().await.unwrap() {
+                    CustomSignal::Shutdown => {
+                        break;
+                    }
+                    CustomSignal::DataUpdate => {
+                        eprintln!("Registry data update triggered");
+                        if let Err(err) = update_whois_data(&registry_root, &updater_whois_data) {
+                            eprintln!("Error updating registry data: {}", err);
+                        }
+                        eprintln!("Registry data update completed")
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        let server = tokio::spawn(server(whois_data, port, bind_ip, sig_chan_tx.subscribe()));
+        let result = tokio::try_join!(
+            async { registry_data_updater.await? },
+            async { server.await? },
+            async { signal_listener_handle.await? }
+        );
+        if let Err(e) = result {
+            return Err(format!("Error: {}", e));
+        }
+        Ok(())
+    })?;
+    Ok("".into())
+}
+
+pub(in crate::modules) async fn server(
+    whois_data: Arc<RwLock<Arc<WhoisData>>>,
+    port: u16,
+    bind_ip: Option<String>,
+    mut signal_rx: broadcast::Receiver<CustomSignal>,
+) -> BoxResult<()> {
+    let bind_ip = if let Some(bind_ip) = bind_ip {
+        IpAddr::from_str(bind_ip.as_str())?
+    } else {
+        IpAddr::from(Ipv6Addr::UNSPECIFIED)
+    };
+
+    let addr = SocketAddr::from((bind_ip, port));
+    let listener = TcpListener::bind(&addr).await
+        .map_err(|x| format!("Error listening on TCP: {}", x))?;
+    println!(
+        "Whois server listening on {}. Send the POSIX 'SIGUSR1' signal to this process to trigger data update",
+        addr
+    );
+    loop {
+        tokio::select! {
+            result = listener.accept() => {
+                match result {
+                    Ok((stream, _)) => {
+                        let data = whois_data.read().unwrap().clone();
+                        tokio::spawn(handle_connection(stream, data));
+                    }
+                    Err(e) => eprintln!("Error accepting connection: {}", e),
+                }
+            }
+            signal = signal_rx.recv() => {
+                if let Ok(CustomSignal::Shutdown) = signal {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Answer a single query line and close the connection (RFC 3912)
+async fn handle_connection(mut stream: TcpStream, whois_data: Arc<WhoisData>) {
+    let mut line = String::new();
+    let mut reader = BufReader::new((&mut stream).take(MAX_QUERY_LENGTH));
+    if !matches!(tokio::time::timeout(QUERY_TIMEOUT, reader.read_line(&mut line)).await, Ok(Ok(_))) {
+        return;
+    }
+    let response = answer_query(&whois_data, line.trim());
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+fn answer_query(whois_data: &WhoisData, line: &str) -> String {
+    let mut output = format!("% dn42 registry whois server (data from commit {})\n\n", whois_data.commit_hash);
+    match parse_query(line) {
+        Err(e) => {
+            output += format!("{}\n\n", e).as_str();
+        }
+        Ok(query) => {
+            let objects: Vec<_> = lookup(whois_data, &query).into_iter()
+                .map(|i| whois_data.graph.node(i))
+                .map(|x| (x.data_dir.as_str(), &x.object))
+                .collect();
+            output += format_whois(&objects).as_str();
+        }
+    }
+    output
+}
+
+fn parse_query(line: &str) -> Result<WhoisQuery, String> {
+    let mut query = WhoisQuery { recursive: true, inverse_keys: Vec::new(), term: String::new() };
+    let mut terms = Vec::new();
+    let mut args = line.split_whitespace();
+    while let Some(arg) = args.next() {
+        match arg {
+            "-r" => query.recursive = false,
+            "-i" => {
+                let keys = args.next().ok_or("%ERROR:106: no search key specified")?;
+                query.inverse_keys = keys.split(',').map(|x| x.to_lowercase()).collect();
+            }
+            x if x.starts_with('-') => return Err(format!("%ERROR:111: invalid option supplied: '{}'", x)),
+            x => terms.push(x),
+        }
+    }
+    if terms.is_empty() {
+        return Err(String::from("%ERROR:106: no search key specified"));
+    }
+    query.term = terms.join(" ");
+    Ok(query)
+}
+
+fn lookup(whois_data: &WhoisData, query: &WhoisQuery) -> Vec<NodeId> {
+    let term = query.term.as_str();
+    let mut result = Vec::new();
+    if !query.inverse_keys.is_empty() {
+        // Inverse lookup: objects referencing the search term in one of the given attributes
+        for &i in &whois_data.objects {
+            let matches = query.inverse_keys.iter().any(|key| {
+                whois_data.graph.node(i).object.key_value.get(key)
+                    .is_some_and(|values| values.iter().any(|(_, v)| v.trim().eq_ignore_ascii_case(term)))
+            });
+            if matches {
+                result.push(i);
+            }
+        }
+    } else if let Some(search_cidr) = parse_ip_or_prefix(term) {
+        // Longest match of the address space assignment and the route object
+        let categories = if search_cidr.is_ipv4() { ["inetnum", "route"] } else { ["inet6num", "route6"] };
+        for category in categories {
+            let candidates = whois_data.category(category);
+            if let Some((i, _)) = find_most_specific(&candidates, |x| x.1, &search_cidr) {
+                result.push(*i);
+            }
+        }
+    } else {
+        let name = normalize_asn(term).unwrap_or_else(|_| term.to_string());
+        if let Some(found) = whois_data.names.get(&name.to_uppercase()) {
+            result.extend(found);
+        }
+    }
+
+    if query.recursive {
+        for i in result.clone() {
+            for contact in &whois_data.contacts[i] {
+                if !result.contains(contact) {
+                    result.push(*contact);
+                }
+            }
+        }
+    }
+    result
+}
+
+fn parse_ip_or_prefix(term: &str) -> Option<IpCidr> {
+    if let Ok(ip) = IpAddr::from_str(term) {
+        return Some(IpCidr::new_host(ip));
+    }
+    IpCidr::from_str(&term.replace('_', "/")).ok()
+}
+
+fn update_whois_data(registry_root: &PathBuf, whois_data: &Arc<RwLock<Arc<WhoisData>>>) -> BoxResult<()> {
+    let schema = print_warnings(parse_registry_schema(registry_root, false)?);
+    let graph: ServerGraph = print_warnings(create_registry_graph(registry_root, &schema, false, false)?);
+    let commit_hash = get_git_commit_hash(registry_root).unwrap_or(String::from("N/A"));
+    *whois_data.write().unwrap() = Arc::new(build_whois_data(Arc::new(graph), commit_hash));
+    Ok(())
+}
+
+/// Build the whois lookup data on a registry graph, which may be shared with other servers
+pub(in crate::modules) fn build_whois_data(graph: Arc<ServerGraph>, commit_hash: String) -> WhoisData {
+    let mut categories: Vec<&String> = graph.categories().keys().collect();
+    categories.sort();
+    let objects: Vec<NodeId> = categories.iter()
+        .flat_map(|x| graph.categories()[*x].iter().copied())
+        .collect();
+    let mut names: HashMap<String, Vec<NodeId>> = HashMap::new();
+    for &id in &objects {
+        names.entry(graph.node(id).object.filename.to_uppercase()).or_default().push(id);
+    }
+    let contacts = graph.nodes().map(|(_, elem)| {
+        let mut contacts = Vec::new();
+        for (_, target) in elem.get_forward_links() {
+            if CONTACT_CATEGORIES.contains(&graph.node(*target).schema_ref.as_str()) && !contacts.contains(target) {
+                contacts.push(*target);
+            }
+        }
+        contacts
+    }).collect();
+    WhoisData { graph, objects, contacts, names, commit_hash }
+}
@@ -0,0 +1,335 @@
+use crate::modules::util::{get_current_unix_time, get_git_commit_time, BoxResult};
+use registry_core::object_reader::{read_registry_objects, ObjectLine, RegistryObject, SimpleObjectLine};
+#[cfg(feature = "daemon")]
+use registry_core::registry_graph::{LinkInfoType, RegistryGraph};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub(in crate::modules) const DEFAULT_TTL: u32 = 3600;
+const SOA_RNAME: &str = "hostmaster.dn42.";
+const SOA_REFRESH: u32 = 3600;
+const SOA_RETRY: u32 = 600;
+const SOA_EXPIRE: u32 = 604800;
+const SOA_MINIMUM: u32 = 300;
+const MAX_LABEL_LENGTH: usize = 63;
+const MAX_NAME_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(in crate::modules) enum RecordData {
+    Soa {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    Ns(String),
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Cname(String),
+    Ds(DsRecord),
+}
+
+impl RecordData {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            RecordData::Soa { .. } => "SOA",
+            RecordData::Ns(_) => "NS",
+            RecordData::A(_) => "A",
+            RecordData::Aaaa(_) => "AAAA",
+            RecordData::Cname(_) => "CNAME",
+            RecordData::Ds(_) => "DS",
+        }
+    }
+
+    fn rdata_string(&self) -> String {
+        match self {
+            RecordData::Soa { mname, rname, serial, refresh, retry, expire, minimum } => {
+                format!("{} {} {} {} {} {} {}", mname, rname, serial, refresh, retry, expire, minimum)
+            }
+            RecordData::Ns(x) | RecordData::Cname(x) => x.clone(),
+            RecordData::Ds(x) => x.to_string(),
+            RecordData::A(x) => x.to_string(),
+            RecordData::Aaaa(x) => x.to_string(),
+        }
+    }
+}
+
+/// A parsed and validated 'ds-rdata' value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(in crate::modules) struct DsRecord {
+    pub key_tag: u16,
+    pub algorithm: u8,
+    pub digest_type: u8,
+    pub digest: String,
+}
+
+impl FromStr for DsRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let key_tag = fields.next().ok_or("missing key tag")?;
+        let key_tag = key_tag.parse::<u16>().map_err(|_| format!("invalid key tag '{}'", key_tag))?;
+        let algorithm = fields.next().ok_or("missing algorithm")?;
+        let algorithm = algorithm.parse::<u8>().map_err(|_| format!("invalid algorithm '{}'", algorithm))?;
+        // Assigned DNSSEC algorithm numbers that may be used for zone signing (including private algorithms)
+        if !matches!(algorithm, 1 | 3 | 5..=8 | 10 | 12..=16 | 253 | 254) {
+            return Err(format!("unknown algorithm {}", algorithm));
+        }
+        let digest_type = fields.next().ok_or("missing digest type")?;
+        let digest_type = digest_type.parse::<u8>().map_err(|_| format!("invalid digest type '{}'", digest_type))?;
+        let expected_length = match digest_type {
+            1 => 40,     // SHA-1
+            2 | 3 => 64, // SHA-256, GOST R 34.11-94
+            4 => 96,     // SHA-384
+            _ => return Err(format!("unknown digest type {}", digest_type)),
+        };
+        let digest: String = fields.collect();
+        if digest.is_empty() {
+            return Err("missing digest".into());
+        }
+        if !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("digest '{}' is not hexadecimal", digest));
+        }
+        if digest.len() != expected_length {
+            return Err(format!("digest length {} does not match digest type {} (expected {})",
+                               digest.len(), digest_type, expected_length));
+        }
+        Ok(DsRecord { key_tag, algorithm, digest_type, digest })
+    }
+}
+
+impl Display for DsRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {} {}", self.key_tag, self.algorithm, self.digest_type, self.digest)
+    }
+}
+
+/// The registry objects the authoritative and reverse zones are built from
+pub(in crate::modules) struct ZoneObjects<'a, T: ObjectLine> {
+    /// SOA serial of the zones
+    pub serial: u32,
+    pub dns: Vec<&'a RegistryObject<T>>,
+    pub inetnum: Vec<&'a RegistryObject<T>>,
+    pub inet6num: Vec<&'a RegistryObject<T>>,
+}
+
+impl<'a, T: ObjectLine> ZoneObjects<'a, T> {
+    pub fn new(serial: u32, dns: &'a [RegistryObject<T>], inetnum: &'a [RegistryObject<T>], inet6num: &'a [RegistryObject<T>]) -> Self {
+        ZoneObjects {
+            serial,
+            dns: dns.iter().collect(),
+            inetnum: inetnum.iter().collect(),
+            inet6num: inet6num.iter().collect(),
+        }
+    }
+
+    /// Use the objects of an already built registry graph instead of reading them again
+    #[cfg(feature = "daemon")]
+    pub fn from_graph<L: LinkInfoType<T>>(graph: &'a RegistryGraph<T, L>, serial: u32) -> Self {
+        let objects = |category: &str| -> Vec<&'a RegistryObject<T>> {
+            graph.category(category).unwrap_or_default().iter().map(|id| &graph.node(*id).object).collect()
+        };
+        ZoneObjects { serial, dns: objects("dns"), inetnum: objects("inetnum"), inet6num: objects("inet6num") }
+    }
+}
+
+/// Read the objects of a registry directory used for zone building (i.e. `data/dns`)
+pub(in crate::modules) fn read_zone_objects(registry_root: &Path, dir_name: &str) -> BoxResult<Vec<RegistryObject<SimpleObjectLine>>> {
+    read_registry_objects(registry_root, &Path::new("data").join(dir_name), false)
+}
+
+/// The SOA serial for zones built from the registry: the time of the current commit
+pub(in crate::modules) fn zone_serial(registry_root: &Path) -> u32 {
+    get_git_commit_time(registry_root).unwrap_or_else(get_current_unix_time) as u32
+}
+
+/// The values of an object attribute
+pub(in crate::modules) fn object_values<T: ObjectLine>(object: &RegistryObject<T>, key: &str) -> Vec<String> {
+    object.key_value.get(key)
+        .map(|lines| lines.iter().map(|x| x.get_line_value()).collect())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+pub(in crate::modules) struct ResourceRecord {
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+#[derive(Debug, Clone)]
+pub(in crate::modules) struct Zone {
+    pub origin: String,
+    pub records: Vec<ResourceRecord>,
+    /// Names and data of the records, to skip duplicates without scanning the records
+    known: HashSet<(String, RecordData)>,
+}
+
+impl Zone {
+    /// Create a zone containing the SOA and apex NS records. The first nameserver is used as the SOA primary.
+    pub fn new(origin: &str, serial: u32, apex_nameservers: &[String]) -> Self {
+        let origin = fqdn(origin);
+        let mut zone = Zone { origin: origin.clone(), records: Vec::new(), known: HashSet::new() };
+        zone.add(&origin, RecordData::Soa {
+            mname: apex_nameservers.first().map(|x| fqdn(x)).unwrap_or_else(|| origin.clone()),
+            rname: SOA_RNAME.to_string(),
+            serial,
+            refresh: SOA_REFRESH,
+            retry: SOA_RETRY,
+            expire: SOA_EXPIRE,
+            minimum: SOA_MINIMUM,
+        });
+        for ns in apex_nameservers {
+            zone.add(&origin, RecordData::Ns(fqdn(ns)));
+        }
+        zone
+    }
+
+    /// Add a record unless an identical one already exists. Records with names that cannot be encoded are skipped.
+    pub fn add(&mut self, name: &str, data: RecordData) {
+        let name = fqdn(name);
+        let target = match &data {
+            RecordData::Soa { mname, .. } => Some(mname),
+            RecordData::Ns(x) | RecordData::Cname(x) => Some(x),
+            _ => None,
+        };
+        if let Err(e) = check_name(&name).and_then(|_| target.map_or(Ok(()), |x| check_name(x))) {
+            eprintln!("Warning: Skipping {} record for '{}' in zone {}: {}", data.type_name(), name, self.origin, e);
+            return;
+        }
+        if !self.known.insert((name.clone(), data.clone())) {
+            return;
+        }
+        self.records.push(ResourceRecord { name, ttl: DEFAULT_TTL, data });
+    }
+
+    /// Add the nameserver address as glue if the nameserver is inside this zone
+    pub fn add_glue(&mut self, nameserver: &str, address: IpAddr) {
+        if !is_subdomain(nameserver, &self.origin) {
+            return;
+        }
+        match address {
+            IpAddr::V4(v4) => self.add(nameserver, RecordData::A(v4)),
+            IpAddr::V6(v6) => self.add(nameserver, RecordData::Aaaa(v6)),
+        }
+    }
+
+    pub fn to_zone_file(&self) -> String {
+        let mut output = String::new();
+        output += &format!("$ORIGIN {}\n", self.origin);
+        output += &format!("$TTL {}\n", DEFAULT_TTL);
+        for record in &self.records {
+            output += &format!("{:<48} {:<6} IN {:<5} {}\n",
+                               record.name, record.ttl, record.data.type_name(), record.data.rdata_string());
+        }
+        output
+    }
+}
+
+/// Output the zones (optionally only the ones in `zone_filter`) to stdout or as '<zone>.zone' files in `output_dir`
+pub(in crate::modules) fn write_zones(mut zones: Vec<Zone>, zone_filter: &[String], output_dir: Option<PathBuf>) -> BoxResult<String> {
+    if !zone_filter.is_empty() {
+        zones.retain(|z| zone_filter.iter().any(|f| z.origin == fqdn(f)));
+        if zones.is_empty() {
+            return Err("None of the specified zones are known".into());
+        }
+    }
+
+    let mut output = String::new();
+    for zone in zones {
+        if let Some(ref output_dir) = output_dir {
+            let path = output_dir.join(format!("{}zone", zone.origin));
+            fs::write(&path, zone.to_zone_file())
+                .map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+            output += format!("{}\n", path.display()).as_str();
+        } else {
+            output += zone.to_zone_file().as_str();
+            output += "\n";
+        }
+    }
+    Ok(output)
+}
+
+/// Lowercase a domain name and ensure it is fully qualified
+pub(in crate::modules) fn fqdn(name: &str) -> String {
+    let mut name = name.trim().to_lowercase();
+    if !name.ends_with('.') {
+        name.push('.');
+    }
+    name
+}
+
+/// Check that a domain name can be encoded in DNS messages (RFC 1035 2.3.4)
+pub(in crate::modules) fn check_name(name: &str) -> Result<(), String> {
+    let name = name.trim_end_matches('.');
+    if name.is_empty() {
+        return Ok(());
+    }
+    let mut length = 1;
+    for label in name.split('.') {
+        if label.is_empty() {
+            return Err(format!("empty label in '{}'", name));
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(format!("label '{}' is longer than {} bytes", label, MAX_LABEL_LENGTH));
+        }
+        length += label.len() + 1;
+    }
+    if length > MAX_NAME_LENGTH {
+        return Err(format!("'{}' is longer than {} bytes", name, MAX_NAME_LENGTH));
+    }
+    Ok(())
+}
+
+/// Check the nameserver names of an object's 'nserver' lines
+pub(in crate::modules) fn check_nserver_names(n_server: &[String]) -> Result<(), String> {
+    for line in n_server {
+        let (host, _) = parse_nserver(line);
+        check_name(&host).map_err(|e| format!("invalid nserver '{}': {}", host, e))?;
+    }
+    Ok(())
+}
+
+pub(in crate::modules) fn is_subdomain(name: &str, parent: &str) -> bool {
+    let name = fqdn(name);
+    let parent = fqdn(parent);
+    name == parent || name.ends_with(&(".".to_owned() + &parent))
+}
+
+/// Report an invalid object: an error in strict mode, a warning (skipping the object) otherwise
+pub(in crate::modules) fn report_invalid(message: String, strict: bool) -> BoxResult<()> {
+    if strict {
+        return Err(message.into());
+    }
+    eprintln!("Warning: {}", message);
+    Ok(())
+}
+
+/// Parse the ds-rdata values of an object. Invalid ones are an error in strict mode and skipped (and reported) otherwise.
+pub(in crate::modules) fn parse_ds_rdata_list(ds_rdata: &[String], object_name: &str, strict: bool) -> Result<Vec<DsRecord>, String> {
+    let mut records = Vec::new();
+    for ds in ds_rdata {
+        match DsRecord::from_str(ds) {
+            Ok(record) => records.push(record),
+            Err(e) if strict => return Err(format!("invalid ds-rdata '{}': {}", ds, e)),
+            Err(e) => eprintln!("Warning: Ignoring invalid ds-rdata '{}' for {}: {}", ds, object_name, e),
+        }
+    }
+    Ok(records)
+}
+
+/// Split a registry 'nserver' line into the nameserver hostname and the optional IP address
+pub(in crate::modules) fn parse_nserver(line: &str) -> (String, Option<IpAddr>) {
+    let mut fields = line.split_whitespace();
+    let host = fields.next().unwrap_or_default().to_string();
+    let address = fields.next().and_then(|x| IpAddr::from_str(x).ok());
+    (host, address)
+}
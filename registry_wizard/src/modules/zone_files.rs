@@ -1,12 +1,13 @@
 use std::collections::HashMap;
-use crate::modules::object_reader;
-use crate::modules::object_reader::registry_objects_to_iter;
+use registry_core::object_reader;
+use registry_core::object_reader::{read_registry_objects, registry_objects_to_iter, SimpleObjectLine};
 use crate::modules::util::BoxResult;
+use crate::modules::zone_builder::{parse_ds_rdata_list, parse_nserver, DsRecord};
 use std::net::IpAddr;
 use std::path::Path;
 use std::str::FromStr;
 
-static STATIC_ENTRIES: [(&str, &str); 7] = [
+pub(in crate::modules) static STATIC_ENTRIES: [(&str, &str); 7] = [
     ("20.172.in-addr.arpa", "inetnum/172.20.0.0_16"),
     ("21.172.in-addr.arpa", "inetnum/172.21.0.0_16"),
     ("22.172.in-addr.arpa", "inetnum/172.22.0.0_16"),
@@ -16,47 +17,96 @@ static STATIC_ENTRIES: [(&str, &str); 7] = [
     ("d.f.ip6.arpa", "inet6num/fd00::_8")
 ];
 
+/// Maximum number of data/dns objects consulted when resolving a '.dn42' nameserver from the registry
+const MAX_RESOLVE_DEPTH: usize = 8;
 
-pub fn output_forward_zones(registry_root: &Path, auth_servers: Vec<String>) -> BoxResult<String> {
+
+/// Resolver software to generate configuration for
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ResolverFormat {
+    PowerDns,
+    Unbound,
+    Knot,
+    Bind,
+    Dnsmasq,
+}
+
+pub fn output_forward_zones(registry_root: &Path, auth_servers: Vec<String>, format: ResolverFormat, strict: bool) -> BoxResult<String> {
     let mut output = String::new();
     let auth_servers = auth_servers.iter().map(|s| {
         IpAddr::from_str(s)
-            .map(|parsed| {
-                if parsed.is_ipv6() {
-                    String::from('\'') + &*parsed.to_string() + "\'"
-                } else {
-                    parsed.to_string()
-                }
-            }).map_err(|e| format!("Could not parse provided authoritative server IP '{}' : {}", s, e))
-    }).collect::<Result<Vec<String>, String>>()?;
+            .map(|parsed| (parsed.to_string(), parsed.is_ipv6()))
+            .map_err(|e| format!("Could not parse provided authoritative server IP '{}' : {}", s, e))
+    }).collect::<Result<Vec<(String, bool)>, String>>()?;
 
-    let mut objects = read_tld_objects(registry_root, true)
+    let mut objects = read_tld_objects(registry_root, true, strict)
         .map_err(|e| format!("Error reading objects: {}", e))?;
     objects.sort_by(|a, b| a.tld.cmp(&b.tld));
-    output += "recursor:\n";
-    output += "  forward_zones:\n";
+    if format == ResolverFormat::PowerDns {
+        output += "recursor:\n";
+        output += "  forward_zones:\n";
+    }
     for object in objects {
-        let hoist;
-        let current_auth_servers: &Vec<String> = if object.n_server_v4.is_empty() && object.n_server_v6.is_empty() {
-            &auth_servers
+        let forwarders: Vec<(String, bool)> = if object.n_server_v4.is_empty() && object.n_server_v6.is_empty() {
+            auth_servers.clone()
         } else {
-            hoist = object.n_server_v4.into_iter().chain(object.n_server_v6.into_iter().map(|s| "'".to_owned() + &*s + "'")).collect();
-            &hoist
+            object.n_server_v4.into_iter().map(|s| (s, false))
+                .chain(object.n_server_v6.into_iter().map(|s| (s, true))).collect()
         };
-        output += format!("  - zone: '{}'\n", object.tld).as_str();
-        output += "    forwarders:\n";
-        for auth_server in current_auth_servers {
-            output += format!("    - {}\n", auth_server).as_str();
-        }
+        output += format_forward_zone(format, &object.tld, &forwarders).as_str();
     }
     Ok(output)
 }
 
+fn format_forward_zone(format: ResolverFormat, zone: &str, forwarders: &[(String, bool)]) -> String {
+    let mut output = String::new();
+    match format {
+        ResolverFormat::PowerDns => {
+            output += format!("  - zone: '{}'\n", zone).as_str();
+            output += "    forwarders:\n";
+            for (forwarder, is_v6) in forwarders {
+                if *is_v6 {
+                    output += format!("    - '{}'\n", forwarder).as_str();
+                } else {
+                    output += format!("    - {}\n", forwarder).as_str();
+                }
+            }
+        }
+        // The forwarders are authoritative servers, therefore stub zones (non-recursive queries following
+        // referrals) are used instead of forwarding
+        ResolverFormat::Unbound => {
+            output += "stub-zone:\n";
+            output += format!("    name: \"{}\"\n", zone).as_str();
+            for (forwarder, _) in forwarders {
+                output += format!("    stub-addr: {}\n", forwarder).as_str();
+            }
+        }
+        ResolverFormat::Knot => {
+            let list = forwarders.iter().map(|(f, _)| format!("'{}'", f)).collect::<Vec<_>>().join(", ");
+            output += format!("policy.add(policy.suffix(policy.STUB({{{}}}), {{todname('{}.')}}))\n", list, zone).as_str();
+        }
+        ResolverFormat::Bind => {
+            let list = forwarders.iter().map(|(f, _)| f.as_str()).collect::<Vec<_>>().join("; ");
+            output += format!("zone \"{}\" {{\n", zone).as_str();
+            output += "    type static-stub;\n";
+            output += format!("    server-addresses {{ {}; }};\n", list).as_str();
+            output += "};\n";
+        }
+        ResolverFormat::Dnsmasq => {
+            for (forwarder, _) in forwarders {
+                output += format!("server=/{}/{}\n", zone, forwarder).as_str();
+            }
+            output += format!("rebind-domain-ok=/{}/\n", zone).as_str();
+        }
+    }
+    output
+}
+
 
 
-pub fn output_forward_zones_legacy(registry_root: &Path, auth_servers: Vec<String>) -> BoxResult<String>{
+pub fn output_forward_zones_legacy(registry_root: &Path, auth_servers: Vec<String>, strict: bool) -> BoxResult<String>{
     let mut output = String::new();
-    let mut objects = read_tld_objects(registry_root, true)
+    let mut objects = read_tld_objects(registry_root, true, strict)
         .map_err(|e| format!("Error reading objects: {}", e))?;
     objects.sort_by(|a,b | a.tld.cmp(&b.tld));
     let mut first = true;
@@ -81,56 +131,135 @@ pub fn output_forward_zones_legacy(registry_root: &Path, auth_servers: Vec<Strin
     Ok(output)
 }
 
-pub fn output_tas(registry_root: &Path, yaml: bool) -> BoxResult<String> {
+pub fn output_tas(registry_root: &Path, format: ResolverFormat, yaml: bool, strict: bool) -> BoxResult<String> {
     let mut output = String::new();
-    let objects = read_tld_objects(registry_root, false)
+    let objects = read_tld_objects(registry_root, false, strict)
         .map_err(|e| format!("Error reading objects: {}", e))?;
+    let objects = remove_invalid_trust_anchors(objects, strict)?;
 
-    if yaml {
-        output += "dnssec:\n";
-        output += "  trustanchors:\n";
+    match format {
+        ResolverFormat::PowerDns if yaml => {
+            output += "dnssec:\n";
+            output += "  trustanchors:\n";
 
-        // Group ds-rdata by tld
-        let mut grouped: HashMap<&String, Vec<&String>> = HashMap::new();
-        for obj in objects.iter().filter(|x| !x.ds_rdata.is_empty()) {
-            grouped.entry(&obj.tld).or_default().extend(obj.ds_rdata.iter());
-        }
+            // Group ds-rdata by tld
+            let mut grouped: HashMap<&String, Vec<&DsRecord>> = HashMap::new();
+            for obj in objects.iter().filter(|x| !x.ds_rdata.is_empty()) {
+                grouped.entry(&obj.tld).or_default().extend(obj.ds_rdata.iter());
+            }
 
-        for (tld, records) in grouped {
-            output += format!("    - name: '{}'\n", tld).as_str();
-            output += "      dsrecords:\n";
-            for record in records {
-                output += format!("      - '{}'\n", record).as_str();
+            for (tld, records) in grouped {
+                output += format!("    - name: '{}'\n", tld).as_str();
+                output += "      dsrecords:\n";
+                for record in records {
+                    output += format!("      - '{}'\n", record).as_str();
+                }
             }
-        }
 
-        output += "  negative_trustanchors:\n";
-        for object in objects.iter().filter(|x| x.ds_rdata.is_empty()) {
-            output += format!("    - name: '{}'\n", object.tld).as_str();
-            output += format!("      reason: 'No known trust anchor for zone {}'\n", object.tld).as_str();
+            output += "  negative_trustanchors:\n";
+            for object in objects.iter().filter(|x| x.ds_rdata.is_empty()) {
+                output += format!("    - name: '{}'\n", object.tld).as_str();
+                output += format!("      reason: 'No known trust anchor for zone {}'\n", object.tld).as_str();
+            }
         }
-
-    } else {
-        for object in objects {
-            if object.ds_rdata.is_empty() {
-                output += format!("addNTA('{}','No known trust anchor for zone {}')\n", object.tld, object.tld).as_str();
-                continue;
+        ResolverFormat::PowerDns => {
+            for object in objects {
+                if object.ds_rdata.is_empty() {
+                    output += format!("addNTA('{}','No known trust anchor for zone {}')\n", object.tld, object.tld).as_str();
+                    continue;
+                }
+                for ta in object.ds_rdata {
+                    output += format!("addTA('{}','{}')\n", object.tld, ta).as_str();
+                }
+            };
+        }
+        ResolverFormat::Unbound => {
+            output += "server:\n";
+            for object in objects {
+                if object.ds_rdata.is_empty() {
+                    output += format!("    domain-insecure: \"{}\"\n", object.tld).as_str();
+                    continue;
+                }
+                for ta in object.ds_rdata {
+                    output += format!("    trust-anchor: \"{}. DS {}\"\n", object.tld, ta).as_str();
+                }
+            }
+        }
+        ResolverFormat::Knot => {
+            let mut insecure = Vec::new();
+            for object in objects {
+                if object.ds_rdata.is_empty() {
+                    insecure.push(format!("'{}.'", object.tld));
+                    continue;
+                }
+                for ta in object.ds_rdata {
+                    output += format!("trust_anchors.add('{}. DS {}')\n", object.tld, ta).as_str();
+                }
             }
-            for ta in object.ds_rdata {
-                output += format!("addTA('{}','{}')\n", object.tld, ta).as_str();
+            if !insecure.is_empty() {
+                output += format!("trust_anchors.set_insecure({{{}}})\n", insecure.join(", ")).as_str();
             }
-        };
+        }
+        ResolverFormat::Bind => {
+            let mut insecure = Vec::new();
+            output += "trust-anchors {\n";
+            for object in objects {
+                if object.ds_rdata.is_empty() {
+                    insecure.push(format!("\"{}\"", object.tld));
+                    continue;
+                }
+                for ta in object.ds_rdata {
+                    output += format!("    {}. static-ds {} {} {} \"{}\";\n", object.tld, ta.key_tag, ta.algorithm, ta.digest_type, ta.digest).as_str();
+                }
+            }
+            output += "};\n";
+            if !insecure.is_empty() {
+                output += "// Negative trust anchors (belongs in the options block)\n";
+                output += format!("validate-except {{ {}; }};\n", insecure.join("; ")).as_str();
+            }
+        }
+        ResolverFormat::Dnsmasq => {
+            for object in objects {
+                if object.ds_rdata.is_empty() {
+                    output += format!("# No known trust anchor for zone {} (dnsmasq does not support negative trust anchors)\n", object.tld).as_str();
+                    continue;
+                }
+                for ta in object.ds_rdata {
+                    output += format!("trust-anchor={},{},{},{},{}\n", object.tld, ta.key_tag, ta.algorithm, ta.digest_type, ta.digest).as_str();
+                }
+            }
+        }
     }
 
     Ok(output)
 }
 
+/// Remove the zones whose ds-rdata values are all invalid. Without a trust anchor they would be output as
+/// negative trust anchors, disabling DNSSEC validation for them.
+fn remove_invalid_trust_anchors(objects: Vec<TldObject>, strict: bool) -> BoxResult<Vec<TldObject>> {
+    let mut result = Vec::with_capacity(objects.len());
+    for object in objects {
+        if !object.invalid_ds_rdata {
+            result.push(object);
+            continue;
+        }
+        let message = format!("{}: none of the ds-rdata values are valid", object.tld);
+        if strict {
+            return Err(message.into());
+        }
+        eprintln!("Warning: Skipping trust anchor output for {} (the zone is not marked insecure)", message);
+    }
+    Ok(result)
+}
+
 #[derive(Debug)]
 struct TldObject<> {
     tld: String,
     n_server_v4: Vec<String>,
     n_server_v6: Vec<String>,
-    ds_rdata: Vec<String>,
+    ds_rdata: Vec<DsRecord>,
+    /// The object has ds-rdata values but none of them are valid
+    invalid_ds_rdata: bool,
 }
 
 #[derive(Debug)]
@@ -150,12 +279,18 @@ impl TldObjectBuilder {
             mnt: None,
         }
     }
-    fn build(self, is_reverse: bool, show_nameserver_note: bool) -> BoxResult<TldObject> {
-        if self.tld.is_none() || self.mnt.is_none() || self.n_server.is_empty() {
-            Err("missing fields")?
+    fn build(self, is_reverse: bool, show_nameserver_note: bool, strict: bool, dns_objects: &HashMap<String, Vec<String>>) -> BoxResult<TldObject> {
+        let missing_fields: Vec<&str> = [
+            ("domain", self.tld.is_none()),
+            ("mnt-by", self.mnt.is_none()),
+            ("nserver", self.n_server.is_empty()),
+        ].into_iter().filter(|(_, missing)| *missing).map(|(field, _)| field).collect();
+        if !missing_fields.is_empty() {
+            Err(format!("missing fields: {}", missing_fields.join(", ")))?
         }
-        if self.mnt.unwrap() != "DN42-MNT" {
-            Err("mnt is not dn42")?
+        let mnt = self.mnt.unwrap();
+        if mnt != "DN42-MNT" {
+            Err(format!("maintained by '{}' instead of DN42-MNT", mnt))?
         }
         if !is_reverse && self.tld.as_ref().unwrap().contains('.') {
             Err("not a tld")?
@@ -171,12 +306,25 @@ impl TldObjectBuilder {
                 } else if server.ends_with(".ipv6.registry-sync.dn42") {
                     let reverse_notation = server.strip_suffix(".ipv6.registry-sync.dn42").unwrap_or_default();
                     n_servers_v6.push(parse_reverse_ip_notation(reverse_notation, true));
-                } else if show_nameserver_note {
-                    eprintln!("Encountered nameserver that needs to be resolved: '{}' for '{}' (Will use provided authoritative servers)", server, self.tld.as_ref().unwrap_or(&"N/A".to_string()));
+                } else {
+                    let addresses = resolve_registry_nameserver(&server, dns_objects);
+                    if addresses.is_empty() && show_nameserver_note {
+                        eprintln!("Encountered nameserver that could not be resolved from the registry: '{}' for '{}' (Will use provided authoritative servers)", server, self.tld.as_ref().unwrap_or(&"N/A".to_string()));
+                    }
+                    for address in addresses {
+                        if address.is_ipv6() {
+                            n_servers_v6.push(address.to_string());
+                        } else {
+                            n_servers_v4.push(address.to_string());
+                        }
+                    }
                 }
             } else if let Some(split_server) = server.split_once(' ') {
                 let parsed_server_ip = IpAddr::from_str(split_server.1);
                 if parsed_server_ip.is_err() {
+                    if strict {
+                        Err(format!("invalid nameserver IP in '{}'", server))?
+                    }
                     eprintln!("Failed to parse nameserver IP: {} for {:?}", server, self.tld);
                     continue;
                 }
@@ -185,14 +333,19 @@ impl TldObjectBuilder {
                 } else {
                     n_servers_v4.push(parsed_server_ip.unwrap().to_string())
                 }
+            } else if strict {
+                Err(format!("unknown nameserver format '{}'", server))?
             } else {
                 eprintln!("Encountered unknown nameserver format: {} for {:?}", server, self.tld);
             }
         }
 
+        let tld = self.tld.unwrap();
+        let ds_rdata = parse_ds_rdata_list(&self.ds_rdata, &tld, strict)?;
         let obj = TldObject {
-            tld: self.tld.unwrap(),
-            ds_rdata: self.ds_rdata,
+            invalid_ds_rdata: !self.ds_rdata.is_empty() && ds_rdata.is_empty(),
+            ds_rdata,
+            tld,
             n_server_v4: n_servers_v4,
             n_server_v6: n_servers_v6,
         };
@@ -200,6 +353,60 @@ impl TldObjectBuilder {
     }
 }
 
+/// Decode the address encoded in a '<reversed address>.ipv4.registry-sync.dn42' or '.ipv6.registry-sync.dn42' name
+pub(in crate::modules) fn decode_registry_sync_name(name: &str) -> Option<IpAddr> {
+    let name = name.trim_end_matches('.');
+    if let Some(reverse_notation) = name.strip_suffix(".ipv4.registry-sync.dn42") {
+        IpAddr::from_str(&parse_reverse_ip_notation(reverse_notation, false)).ok()
+    } else if let Some(reverse_notation) = name.strip_suffix(".ipv6.registry-sync.dn42") {
+        IpAddr::from_str(&parse_reverse_ip_notation(reverse_notation, true)).ok()
+    } else {
+        None
+    }
+}
+
+/// Look up the addresses of a '.dn42' nameserver using the glue in the data/dns object closest to the name,
+/// moving up through the parent domain objects until addresses are found
+fn resolve_registry_nameserver(host: &str, dns_objects: &HashMap<String, Vec<String>>) -> Vec<IpAddr> {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let mut domain = host.as_str();
+    let mut depth = 0;
+    while depth < MAX_RESOLVE_DEPTH {
+        if let Some(n_servers) = dns_objects.get(domain) {
+            depth += 1;
+            let mut addresses = Vec::new();
+            for n_server in n_servers {
+                let (name, address) = parse_nserver(n_server);
+                if let Some(address) = address
+                    && name.trim_end_matches('.').eq_ignore_ascii_case(&host)
+                    && !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+            if !addresses.is_empty() {
+                return addresses;
+            }
+        }
+        let Some((_, parent)) = domain.split_once('.') else {
+            break;
+        };
+        domain = parent;
+    }
+    Vec::new()
+}
+
+/// Read the nserver lines of all data/dns objects, keyed by the lowercase domain name
+fn read_dns_nameservers(registry_root: &Path) -> BoxResult<HashMap<String, Vec<String>>> {
+    let mut dns_objects = HashMap::new();
+    for object in read_registry_objects::<SimpleObjectLine>(registry_root, Path::new("data/dns"), false)? {
+        let domain = object.key_value.get("domain").and_then(|x| x.first()).unwrap_or(&object.filename);
+        if let Some(n_server) = object.key_value.get("nserver") {
+            dns_objects.insert(domain.trim_end_matches('.').to_lowercase(), n_server.clone());
+        }
+    }
+    Ok(dns_objects)
+}
+
 fn parse_reverse_ip_notation(n: &str, is_v6: bool) -> String {
     let fields = n.split('.').rev();
     let mut result: String = String::new();
@@ -222,8 +429,10 @@ fn parse_reverse_ip_notation(n: &str, is_v6: bool) -> String {
 }
 
 
-fn read_tld_objects(registry_root: &Path, show_nameserver_note: bool) -> BoxResult<Vec<TldObject>> {
+/// Read the TLD objects and the static reverse entries. Invalid objects abort in strict mode and are skipped otherwise.
+fn read_tld_objects(registry_root: &Path, show_nameserver_note: bool, strict: bool) -> BoxResult<Vec<TldObject>> {
     let mut tld_objects: Vec<TldObject> = Vec::new();
+    let dns_objects = read_dns_nameservers(registry_root)?;
     let mut registry_objects = registry_objects_to_iter(registry_root, Path::new("data/dns"))?;
     registry_objects.add_filename_filter(".");
     registry_objects.add_exclusive_fields(vec![
@@ -243,18 +452,36 @@ fn read_tld_objects(registry_root: &Path, show_nameserver_note: bool) -> BoxResu
         if let Some(v) = obj.key_value.get("nserver") {
             tld_builder.n_server.extend(v.iter().cloned());
         }
-        tld_objects.push(tld_builder.build(false, show_nameserver_note)?)
+        let result = tld_builder.build(false, show_nameserver_note, strict, &dns_objects)
+            .map_err(|e| format!("data/dns/{}: {}", obj.filename, e));
+        if let Some(tld_object) = handle_object_result(result, strict)? {
+            tld_objects.push(tld_object);
+        }
     }
 
     for entry in STATIC_ENTRIES {
-        tld_objects.push(get_static_entry(registry_root, entry, show_nameserver_note)?);
+        let result = get_static_entry(registry_root, entry, show_nameserver_note, strict, &dns_objects)
+            .map_err(|e| format!("data/{}: {}", entry.1, e));
+        if let Some(tld_object) = handle_object_result(result, strict)? {
+            tld_objects.push(tld_object);
+        }
     }
 
     Ok(tld_objects)
 }
 
+fn handle_object_result(result: Result<TldObject, String>, strict: bool) -> BoxResult<Option<TldObject>> {
+    match result {
+        Ok(tld_object) => Ok(Some(tld_object)),
+        Err(e) if strict => Err(e.into()),
+        Err(e) => {
+            eprintln!("Warning: Skipping {}", e);
+            Ok(None)
+        }
+    }
+}
 
-fn get_static_entry(registry_root: &Path, entry: (&str, &str), show_nameserver_note: bool) -> BoxResult<TldObject> {
+fn get_static_entry(registry_root: &Path, entry: (&str, &str), show_nameserver_note: bool, strict: bool, dns_objects: &HashMap<String, Vec<String>>) -> BoxResult<TldObject> {
     let file = registry_root.join("data").join(entry.1);
     let registry_kv = object_reader::read_registry_object_kv(&file)?;
 
@@ -268,6 +495,6 @@ fn get_static_entry(registry_root: &Path, entry: (&str, &str), show_nameserver_n
         tld_builder.n_server.extend(v.iter().cloned());
     }
 
-    let result = tld_builder.build(true, show_nameserver_note)?;
+    let result = tld_builder.build(true, show_nameserver_note, strict, dns_objects)?;
     Ok(result)
 }